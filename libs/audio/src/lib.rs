@@ -0,0 +1,8 @@
+mod mixer;
+pub use self::mixer::*;
+
+mod synth;
+pub use self::synth::*;
+
+mod wavetable;
+pub use self::wavetable::*;
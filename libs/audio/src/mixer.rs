@@ -0,0 +1,134 @@
+use crate::synth::render_sfx;
+
+use platform_types::SFX;
+
+// The game is updated this many times a second, whatever the platform.
+pub const FRAME_RATE: u32 = 60;
+
+struct Voice {
+    samples: Vec<f32>,
+    position: usize,
+}
+
+// Plays any number of sound effects at once, turning the requests made each frame into a
+// continuous stream of samples.
+pub struct Mixer {
+    pub sample_rate: u32,
+    voices: Vec<Voice>,
+    // Frames don't generally line up with samples, so this keeps track of where the next
+    // one starts, to keep the stream from drifting.
+    frame_count: u64,
+}
+
+impl Mixer {
+    pub fn new(sample_rate: u32) -> Self {
+        Mixer {
+            sample_rate,
+            voices: Vec::with_capacity(8),
+            frame_count: 0,
+        }
+    }
+
+    pub fn is_silent(&self) -> bool {
+        self.voices.is_empty()
+    }
+
+    pub fn play(&mut self, sfx: &SFX) {
+        self.voices.push(Voice {
+            samples: render_sfx(sfx, self.sample_rate),
+            position: 0,
+        });
+    }
+
+    // Overwrites `out` with the sum of everything playing, clipped to -1.0 to 1.0. Voices
+    // are dropped once they have finished.
+    pub fn mix(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = 0.0;
+        }
+
+        for voice in self.voices.iter_mut() {
+            let remaining = &voice.samples[voice.position..];
+            for (sample, &voice_sample) in out.iter_mut().zip(remaining) {
+                *sample += voice_sample;
+            }
+            voice.position = (voice.position + out.len()).min(voice.samples.len());
+        }
+
+        for sample in out.iter_mut() {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+
+        self.voices
+            .retain(|voice| voice.position < voice.samples.len());
+    }
+
+    // Starts the sounds requested during a frame, usually from `Speaker::drain`, then
+    // appends that frame's worth of samples to `out`.
+    pub fn frame<I: IntoIterator<Item = SFX>>(&mut self, requests: I, out: &mut Vec<f32>) {
+        for sfx in requests {
+            self.play(&sfx);
+        }
+
+        let rate = self.sample_rate as u64;
+        let start = self.frame_count * rate / FRAME_RATE as u64;
+        self.frame_count += 1;
+        let end = self.frame_count * rate / FRAME_RATE as u64;
+
+        let old_len = out.len();
+        out.resize(old_len + (end - start) as usize, 0.0);
+        self.mix(&mut out[old_len..]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use platform_types::{Envelope, Waveform};
+
+    // Always high, which is easier to check the sum of than a wave.
+    const LEVEL: SFX = SFX {
+        waveform: Waveform::Square(1.0),
+        start_frequency: 100.0,
+        end_frequency: 100.0,
+        envelope: Envelope {
+            attack: 0.0,
+            decay: 0.0,
+            sustain_level: 0.75,
+            sustain: 0.05,
+            release: 0.0,
+        },
+        volume: 1.0,
+    };
+
+    #[test]
+    fn frames_add_up_to_the_sample_rate_without_drifting() {
+        let mut mixer = Mixer::new(44_100);
+        let mut out = Vec::new();
+
+        for _ in 0..FRAME_RATE {
+            mixer.frame(None, &mut out);
+        }
+
+        assert_eq!(out.len(), 44_100);
+    }
+
+    #[test]
+    fn overlapping_sounds_are_added_and_clipped() {
+        let mut mixer = Mixer::new(8000);
+        let mut out = Vec::new();
+
+        mixer.frame(Some(LEVEL), &mut out);
+        assert_eq!(out[10], 0.75);
+
+        out.clear();
+        mixer.frame(Some(LEVEL), &mut out);
+        // The first one is still going, and adding the second goes past the maximum.
+        assert_eq!(out[10], 1.0);
+
+        for _ in 0..5 {
+            mixer.frame(None, &mut out);
+        }
+        assert!(mixer.is_silent());
+    }
+}
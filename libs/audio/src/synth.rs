@@ -0,0 +1,203 @@
+use platform_types::{Envelope, Waveform, SFX};
+
+use std::f32::consts::PI;
+
+// How loud the envelope is `t` seconds in, from 0.0 to 1.0.
+pub fn envelope_level(envelope: &Envelope, t: f32) -> f32 {
+    let Envelope {
+        attack,
+        decay,
+        sustain_level,
+        sustain,
+        release,
+    } = *envelope;
+
+    if t < 0.0 {
+        return 0.0;
+    }
+    if t < attack {
+        return t / attack;
+    }
+
+    let t = t - attack;
+    if t < decay {
+        return 1.0 + (sustain_level - 1.0) * t / decay;
+    }
+
+    let t = t - decay;
+    if t < sustain {
+        return sustain_level;
+    }
+
+    let t = t - sustain;
+    if t < release {
+        return sustain_level * (1.0 - t / release);
+    }
+
+    0.0
+}
+
+// A xorshift generator, so the noise comes out the same every time. That keeps rendered
+// effects comparable between runs.
+struct Noise(u32);
+
+impl Noise {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+}
+
+pub struct Oscillator {
+    pub waveform: Waveform,
+    // How far through the current cycle the next sample is, from 0.0 to 1.0.
+    phase: f32,
+    noise: Noise,
+    noise_level: f32,
+}
+
+impl Oscillator {
+    pub fn new(waveform: Waveform) -> Self {
+        let mut noise = Noise(0x2545_F491);
+        let noise_level = noise.next();
+
+        Oscillator {
+            waveform,
+            phase: 0.0,
+            noise,
+            noise_level,
+        }
+    }
+
+    // Returns the next sample, from -1.0 to 1.0.
+    pub fn next(&mut self, frequency: f32, sample_rate: u32) -> f32 {
+        let p = self.phase;
+        let sample = match self.waveform {
+            Waveform::Sine => (2.0 * PI * p).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (p - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * p - 1.0,
+            Waveform::Square(duty) => {
+                if p < duty {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Noise => self.noise_level,
+        };
+
+        self.phase += frequency.max(0.0) / sample_rate as f32;
+        if self.phase >= 1.0 {
+            self.phase = self.phase.fract();
+            self.noise_level = self.noise.next();
+        }
+
+        sample
+    }
+}
+
+// The sweep is exponential, so it moves through each octave in the same amount of time.
+fn frequency_at(sfx: &SFX, fraction: f32) -> f32 {
+    if sfx.start_frequency <= 0.0 || sfx.end_frequency <= 0.0 {
+        return sfx.start_frequency + (sfx.end_frequency - sfx.start_frequency) * fraction;
+    }
+
+    sfx.start_frequency * (sfx.end_frequency / sfx.start_frequency).powf(fraction)
+}
+
+pub fn render_sfx(sfx: &SFX, sample_rate: u32) -> Vec<f32> {
+    let duration = sfx.envelope.duration();
+    let length = (duration * sample_rate as f32).round() as usize;
+
+    let mut oscillator = Oscillator::new(sfx.waveform);
+    (0..length)
+        .map(|i| {
+            let t = i as f32 / sample_rate as f32;
+            let frequency = frequency_at(sfx, t / duration);
+
+            oscillator.next(frequency, sample_rate) * envelope_level(&sfx.envelope, t) * sfx.volume
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENVELOPE: Envelope = Envelope {
+        attack: 0.1,
+        decay: 0.1,
+        sustain_level: 0.5,
+        sustain: 0.2,
+        release: 0.1,
+    };
+
+    fn sfx(waveform: Waveform, start_frequency: f32, end_frequency: f32) -> SFX {
+        SFX {
+            waveform,
+            start_frequency,
+            end_frequency,
+            envelope: ENVELOPE,
+            volume: 1.0,
+        }
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.001
+    }
+
+    #[test]
+    fn the_envelope_goes_through_each_stage() {
+        let level = |t| envelope_level(&ENVELOPE, t);
+
+        assert!(close(level(0.05), 0.5));
+        assert!(close(level(0.1), 1.0));
+        assert!(close(level(0.15), 0.75));
+        assert!(close(level(0.3), 0.5));
+        assert!(close(level(0.45), 0.25));
+        assert_eq!(level(0.5), 0.0);
+        assert_eq!(level(1.0), 0.0);
+    }
+
+    // Counts the times the signal goes from negative to positive, which is once a cycle.
+    fn cycles(samples: &[f32]) -> usize {
+        samples
+            .windows(2)
+            .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+            .count()
+    }
+
+    #[test]
+    fn effects_last_as_long_as_their_envelope_at_the_right_pitch() {
+        let samples = render_sfx(&sfx(Waveform::Square(0.5), 100.0, 100.0), 8000);
+
+        assert_eq!(samples.len(), 4000);
+        assert_eq!(samples[0], 0.0);
+        // The last cycle ends as the release does, so isn't counted.
+        assert_eq!(cycles(&samples), 49);
+    }
+
+    #[test]
+    fn sweeps_pass_through_every_octave_at_the_same_rate() {
+        let samples = render_sfx(&sfx(Waveform::Sawtooth, 100.0, 400.0), 8000);
+
+        // Half way through the effect, 0.25 seconds in, it should be one octave up.
+        assert!(close(
+            frequency_at(&sfx(Waveform::Sine, 100.0, 400.0), 0.5),
+            200.0
+        ));
+        assert!(cycles(&samples[..2000]) < cycles(&samples[2000..]));
+    }
+
+    #[test]
+    fn noise_is_repeatable() {
+        let effect = sfx(Waveform::Noise, 1000.0, 50.0);
+
+        let samples = render_sfx(&effect, 8000);
+
+        assert_eq!(samples, render_sfx(&effect, 8000));
+        assert!(samples.iter().any(|&s| s > 0.1) && samples.iter().any(|&s| s < -0.1));
+    }
+}
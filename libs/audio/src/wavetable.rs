@@ -0,0 +1,137 @@
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+
+// Notes are MIDI note numbers, so 60 is middle C and 69 is the A above it.
+pub fn note_frequency(note: u8) -> f32 {
+    440.0 * 2f32.powf((note as f32 - 69.0) / 12.0)
+}
+
+pub fn note_name(note: u8) -> String {
+    const NAMES: [&str; 12] = [
+        "c", "c#", "d", "d#", "e", "f", "f#", "g", "g#", "a", "a#", "b",
+    ];
+
+    format!("{}{}", NAMES[note as usize % 12], note as i16 / 12 - 1)
+}
+
+// How far the gain moves towards the volume each sample, so starting and stopping fade
+// quickly instead of clicking.
+const GAIN_STEP: f32 = 1.0 / 512.0;
+
+// Plays a single cycle of a waveform over and over. Any length of table works, but the
+// ones here are 256 long, with each entry's full range being -1.0 to 1.0.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WavetableOscillator {
+    // How far through the table the next sample is, in entries.
+    pub phase: f32,
+    gain: f32,
+}
+
+impl WavetableOscillator {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn is_silent(&self) -> bool {
+        self.gain == 0.0
+    }
+
+    // Overwrites `out`, linearly interpolating between the entries. The table can change
+    // between calls, and the phase carries over, so the waveform can be edited while it
+    // is playing.
+    pub fn render(
+        &mut self,
+        table: &[i8],
+        frequency: f32,
+        sample_rate: u32,
+        volume: f32,
+        out: &mut [f32],
+    ) {
+        let len = table.len();
+        if len == 0 {
+            for sample in out.iter_mut() {
+                *sample = 0.0;
+            }
+            return;
+        }
+
+        let step = frequency * len as f32 / sample_rate as f32;
+
+        for sample in out.iter_mut() {
+            self.gain = if self.gain < volume {
+                (self.gain + GAIN_STEP).min(volume)
+            } else {
+                (self.gain - GAIN_STEP).max(volume)
+            };
+
+            let index = self.phase as usize % len;
+            let fraction = self.phase.fract();
+            let (current, next) = (table[index] as f32, table[(index + 1) % len] as f32);
+
+            *sample = (current + (next - current) * fraction) / 128.0 * self.gain;
+
+            self.phase = (self.phase + step) % len as f32;
+        }
+    }
+}
+
+// A clip of the table at a constant pitch, faded in and out so it starts and ends quietly.
+pub fn synthesise_wavetable(
+    table: &[i8],
+    frequency: f32,
+    sample_rate: u32,
+    volume: f32,
+    seconds: f32,
+) -> Vec<f32> {
+    let mut samples = vec![0.0; (sample_rate as f32 * seconds) as usize];
+
+    let mut oscillator = WavetableOscillator::new();
+    oscillator.render(table, frequency, sample_rate, volume, &mut samples);
+
+    let fade_length = ((volume / GAIN_STEP) as usize).min(samples.len());
+    let fade_start = samples.len() - fade_length;
+    for (i, sample) in samples[fade_start..].iter_mut().enumerate() {
+        *sample *= 1.0 - i as f32 / fade_length as f32;
+    }
+
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notes_are_named_and_tuned_like_midi() {
+        assert_eq!(note_frequency(69), 440.0);
+        assert!((note_frequency(60) - 261.63).abs() < 0.01);
+        assert_eq!(note_name(60), "c4");
+        assert_eq!(note_name(70), "a#4");
+    }
+
+    #[test]
+    fn the_waveform_repeats_at_the_frequency() {
+        let mut table = [0i8; 256];
+        for (i, y) in table.iter_mut().enumerate() {
+            *y = if i < 128 { 64 } else { -64 };
+        }
+
+        // 100 samples per cycle, past the fade in.
+        let samples = synthesise_wavetable(&table, 441.0, DEFAULT_SAMPLE_RATE, 1.0, 0.1);
+        for i in 1000..1100 {
+            assert!((samples[i] - samples[i + 100]).abs() < 0.001);
+        }
+        assert!((samples[1010] - 0.5).abs() < 0.001);
+        assert!((samples[1060] + 0.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn clips_start_and_end_silent() {
+        let table = [127i8; 256];
+
+        let samples = synthesise_wavetable(&table, 100.0, 8000, 0.5, 1.0);
+
+        assert!(samples[0].abs() < 0.01);
+        assert!(samples[samples.len() - 1].abs() < 0.01);
+        assert!((samples[4000] - 0.5 * 127.0 / 128.0).abs() < 0.001);
+    }
+}
@@ -0,0 +1,321 @@
+use rendering::Framebuffer;
+
+pub use rendering::{decode_png, nearest_palette_index};
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::error::Error;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+// All the functions here expect pixels in the same layout as the framebuffer, that is
+// `0xAABBGGRR`, which conveniently is RGBA order when viewed as little-endian bytes.
+
+fn rgb_bytes(colour: u32) -> [u8; 3] {
+    let [r, g, b, _a] = colour.to_le_bytes();
+    [r, g, b]
+}
+
+fn extend_with_rgba_bytes(bytes: &mut Vec<u8>, pixels: &[u32]) {
+    for &colour in pixels {
+        bytes.extend_from_slice(&colour.to_le_bytes());
+    }
+}
+
+// Takes the frames one at a time, so they can be made over several calls without keeping
+// them all around. `encode_gif` does the same for frames that are ready all at once.
+pub struct GifEncoder {
+    encoder: gif::Encoder<Vec<u8>>,
+    palette: Vec<u32>,
+    width: u16,
+    height: u16,
+    delay_centis: u16,
+    indices: Vec<u8>,
+}
+
+impl GifEncoder {
+    // `delay_centis` is in hundredths of a second, as GIF requires.
+    pub fn new(
+        width: u16,
+        height: u16,
+        palette: &[u32],
+        delay_centis: u16,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut palette_bytes = Vec::with_capacity(palette.len() * 3);
+        for &colour in palette {
+            palette_bytes.extend_from_slice(&rgb_bytes(colour));
+        }
+
+        let mut encoder = gif::Encoder::new(Vec::new(), width, height, &palette_bytes)?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+
+        Ok(GifEncoder {
+            encoder,
+            palette: palette.to_vec(),
+            width,
+            height,
+            delay_centis,
+            indices: Vec::with_capacity(width as usize * height as usize),
+        })
+    }
+
+    pub fn write_frame(&mut self, frame: &[u32]) -> Result<(), Box<dyn Error>> {
+        let palette = &self.palette;
+
+        self.indices.clear();
+        self.indices.extend(
+            frame
+                .iter()
+                .map(|&colour| nearest_palette_index(palette, colour)),
+        );
+
+        let gif_frame = gif::Frame {
+            width: self.width,
+            height: self.height,
+            delay: self.delay_centis,
+            buffer: Cow::Borrowed(&self.indices),
+            ..gif::Frame::default()
+        };
+
+        self.encoder.write_frame(&gif_frame)?;
+
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(self.encoder.into_inner()?)
+    }
+}
+
+// `delay_centis` is in hundredths of a second, as GIF requires.
+pub fn encode_gif<F, I>(
+    frames: I,
+    width: u16,
+    height: u16,
+    palette: &[u32],
+    delay_centis: u16,
+) -> Result<Vec<u8>, Box<dyn Error>>
+where
+    F: AsRef<[u32]>,
+    I: IntoIterator<Item = F>,
+{
+    let mut encoder = GifEncoder::new(width, height, palette, delay_centis)?;
+    for frame in frames {
+        encoder.write_frame(frame.as_ref())?;
+    }
+
+    encoder.finish()
+}
+
+// The PNG writer keeps hold of what it writes to until it is finished, and gives nothing
+// back then, so the output is shared with it.
+#[derive(Clone, Default)]
+struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Like `GifEncoder`, but for `encode_apng`.
+pub struct ApngEncoder {
+    writer: png::Writer<SharedOutput>,
+    output: SharedOutput,
+    bytes: Vec<u8>,
+}
+
+impl ApngEncoder {
+    // The frame count needs to be known up front since it is written before any of the
+    // frames.
+    pub fn new(
+        frame_count: u32,
+        width: u32,
+        height: u32,
+        delay: (u16, u16),
+    ) -> Result<Self, Box<dyn Error>> {
+        let output = SharedOutput::default();
+
+        let mut encoder = png::Encoder::new(output.clone(), width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(frame_count, 0)?;
+        encoder.set_frame_delay(delay.0, delay.1)?;
+
+        Ok(ApngEncoder {
+            writer: encoder.write_header()?,
+            output,
+            bytes: Vec::with_capacity(width as usize * height as usize * 4),
+        })
+    }
+
+    pub fn write_frame(&mut self, frame: &[u32]) -> Result<(), Box<dyn Error>> {
+        self.bytes.clear();
+        extend_with_rgba_bytes(&mut self.bytes, frame);
+
+        self.writer.write_image_data(&self.bytes)?;
+
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.writer.finish()?;
+
+        Ok(self.output.0.take())
+    }
+}
+
+// Unlike the GIF, this keeps the full colour of every pixel. Frames past `frame_count`
+// are left out.
+pub fn encode_apng<F, I>(
+    frames: I,
+    frame_count: u32,
+    width: u32,
+    height: u32,
+    delay: (u16, u16),
+) -> Result<Vec<u8>, Box<dyn Error>>
+where
+    F: AsRef<[u32]>,
+    I: IntoIterator<Item = F>,
+{
+    let mut encoder = ApngEncoder::new(frame_count, width, height, delay)?;
+    for frame in frames.into_iter().take(frame_count as usize) {
+        encoder.write_frame(frame.as_ref())?;
+    }
+
+    encoder.finish()
+}
+
+pub fn encode_png(pixels: &[u32], width: u32, height: u32) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut output = Vec::new();
+
+    {
+        let mut encoder = png::Encoder::new(&mut output, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+
+        let mut bytes = Vec::with_capacity(pixels.len() * 4);
+        extend_with_rgba_bytes(&mut bytes, pixels);
+
+        writer.write_image_data(&bytes)?;
+        writer.finish()?;
+    }
+
+    Ok(output)
+}
+
+pub fn screenshot(framebuffer: &Framebuffer) -> Result<Vec<u8>, Box<dyn Error>> {
+    encode_png(
+        &framebuffer.buffer,
+        framebuffer.width as u32,
+        framebuffer.height as u32,
+    )
+}
+
+// For runners that have a filesystem. The web frontend downloads the bytes instead.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_screenshot<P: AsRef<std::path::Path>>(
+    framebuffer: &Framebuffer,
+    path: P,
+) -> Result<(), Box<dyn Error>> {
+    std::fs::write(path, screenshot(framebuffer)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PALETTE: [u32; 3] = [0xFF000000, 0xFF0000FF, 0xFFFFFFFF];
+
+    #[test]
+    fn nearest_palette_index_prefers_exact_matches() {
+        for (i, &colour) in PALETTE.iter().enumerate() {
+            assert_eq!(nearest_palette_index(&PALETTE, colour), i as u8);
+        }
+    }
+
+    #[test]
+    fn nearest_palette_index_finds_the_closest_colour() {
+        assert_eq!(nearest_palette_index(&PALETTE, 0xFF1010EE), 1);
+        assert_eq!(nearest_palette_index(&PALETTE, 0xFFEEEEEE), 2);
+    }
+
+    #[test]
+    fn encode_gif_produces_a_gif() {
+        let frames = vec![vec![PALETTE[0]; 4 * 4], vec![PALETTE[1]; 4 * 4]];
+
+        let bytes = encode_gif(&frames, 4, 4, &PALETTE, 10).unwrap();
+
+        assert!(bytes.starts_with(b"GIF89a"));
+        assert_eq!(bytes.last(), Some(&0x3B));
+    }
+
+    #[test]
+    fn encode_apng_produces_an_animated_png() {
+        let frames = vec![vec![PALETTE[0]; 4 * 4], vec![PALETTE[1]; 4 * 4]];
+
+        let bytes = encode_apng(&frames, 2, 4, 4, (1, 60)).unwrap();
+
+        assert!(bytes.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert!(bytes.windows(4).any(|w| w == b"acTL"));
+    }
+
+    #[test]
+    fn encoding_a_frame_at_a_time_matches_encoding_them_all_at_once() {
+        let frames = vec![vec![PALETTE[0]; 4 * 4], vec![PALETTE[2]; 4 * 4]];
+
+        let mut gif = GifEncoder::new(4, 4, &PALETTE, 10).unwrap();
+        let mut apng = ApngEncoder::new(2, 4, 4, (1, 60)).unwrap();
+        for frame in &frames {
+            gif.write_frame(frame).unwrap();
+            apng.write_frame(frame).unwrap();
+        }
+
+        assert_eq!(
+            gif.finish().unwrap(),
+            encode_gif(&frames, 4, 4, &PALETTE, 10).unwrap()
+        );
+        assert_eq!(
+            apng.finish().unwrap(),
+            encode_apng(&frames, 2, 4, 4, (1, 60)).unwrap()
+        );
+    }
+
+    #[test]
+    fn screenshot_round_trips_through_a_png_decoder() {
+        let mut framebuffer = Framebuffer::new();
+        framebuffer.clear_to(rendering::RED);
+        framebuffer.buffer[1] = rendering::BLUE;
+
+        let bytes = screenshot(&framebuffer).unwrap();
+
+        let decoder = png::Decoder::new(&bytes[..]);
+        let mut reader = decoder.read_info().unwrap();
+        let mut decoded = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut decoded).unwrap();
+
+        assert_eq!(&decoded[0..4], &rendering::RED.to_le_bytes());
+        assert_eq!(&decoded[4..8], &rendering::BLUE.to_le_bytes());
+        assert_eq!(
+            decoded.len(),
+            rendering::SCREEN_WIDTH * rendering::SCREEN_HEIGHT * 4
+        );
+    }
+
+    #[test]
+    fn decode_png_undoes_encode_png() {
+        let pixels: Vec<u32> = (0..6).map(|i| PALETTE[i % PALETTE.len()]).collect();
+
+        let bytes = encode_png(&pixels, 3, 2).unwrap();
+
+        assert_eq!(decode_png(&bytes).unwrap(), (pixels, 3, 2));
+    }
+}
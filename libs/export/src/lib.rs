@@ -0,0 +1,5 @@
+mod image;
+pub use self::image::*;
+
+mod wav;
+pub use self::wav::*;
@@ -0,0 +1,106 @@
+use audio::Mixer;
+use platform_types::SFX;
+
+// Samples are expected to be from -1.0 to 1.0, and anything past that is clipped.
+// They are written as 16 bit mono PCM, which anything that plays WAV files can handle.
+pub fn encode_wav(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+    const CHANNELS: u16 = 1;
+    const BITS_PER_SAMPLE: u16 = 16;
+    const BYTES_PER_FRAME: u16 = CHANNELS * BITS_PER_SAMPLE / 8;
+
+    let data_length = samples.len() as u32 * BYTES_PER_FRAME as u32;
+
+    let mut bytes = Vec::with_capacity(44 + data_length as usize);
+
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_length).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+
+    bytes.extend_from_slice(b"fmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    // Uncompressed PCM.
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&CHANNELS.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * BYTES_PER_FRAME as u32).to_le_bytes());
+    bytes.extend_from_slice(&BYTES_PER_FRAME.to_le_bytes());
+    bytes.extend_from_slice(&BITS_PER_SAMPLE.to_le_bytes());
+
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_length.to_le_bytes());
+    for &sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    bytes
+}
+
+// For runners that have a filesystem. The web frontend downloads the bytes instead.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_wav<P: AsRef<std::path::Path>>(
+    samples: &[f32],
+    sample_rate: u32,
+    path: P,
+) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::write(path, encode_wav(samples, sample_rate))?;
+
+    Ok(())
+}
+
+// Collects everything a `Mixer` plays, for runners without any speakers, like tests, so
+// what a sequence of frames sounds like can be checked without a browser.
+pub struct WavSink {
+    pub mixer: Mixer,
+    pub samples: Vec<f32>,
+}
+
+impl WavSink {
+    pub fn new(sample_rate: u32) -> Self {
+        WavSink {
+            mixer: Mixer::new(sample_rate),
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn frame<I: IntoIterator<Item = SFX>>(&mut self, requests: I) {
+        self.mixer.frame(requests, &mut self.samples);
+    }
+
+    // Keeps going until the sounds that are still playing have finished.
+    pub fn finish(&mut self) {
+        while !self.mixer.is_silent() {
+            self.frame(None);
+        }
+    }
+
+    pub fn wav(&self) -> Vec<u8> {
+        encode_wav(&self.samples, self.mixer.sample_rate)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        save_wav(&self.samples, self.mixer.sample_rate, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_wav_writes_a_canonical_header_and_clips_the_samples() {
+        let bytes = encode_wav(&[0.0, 1.0, -2.0], 8000);
+
+        assert_eq!(bytes.len(), 44 + 6);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(&bytes[4..8], &(44u32 + 6 - 8).to_le_bytes());
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        assert_eq!(&bytes[24..28], &8000u32.to_le_bytes());
+        assert_eq!(&bytes[36..40], b"data");
+        assert_eq!(&bytes[44..], &[0, 0, 0xFF, 0x7F, 0x01, 0x80]);
+    }
+}
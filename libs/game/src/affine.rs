@@ -0,0 +1,139 @@
+use crate::game::{Func, Func2};
+
+// `x -> M x + c` over GF(2)^8. Bit `j` of `rows[i]` is the entry of `M` that says whether
+// input bit `j` goes into output bit `i`, and addition is XOR.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AffineMap {
+    pub rows: [u8; 8],
+    pub constant: u8,
+}
+
+impl Default for AffineMap {
+    fn default() -> Self {
+        AffineMap::identity()
+    }
+}
+
+impl AffineMap {
+    pub fn identity() -> Self {
+        let mut rows = [0; 8];
+        for (i, row) in rows.iter_mut().enumerate() {
+            *row = 1 << i;
+        }
+
+        AffineMap { rows, constant: 0 }
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> bool {
+        self.rows[row] & (1 << column) != 0
+    }
+
+    pub fn toggle(&mut self, row: usize, column: usize) {
+        self.rows[row] ^= 1 << column;
+    }
+
+    pub fn toggle_constant(&mut self, bit: usize) {
+        self.constant ^= 1 << bit;
+    }
+
+    pub fn apply(&self, x: u8) -> u8 {
+        let mut output = self.constant;
+        for (i, &row) in self.rows.iter().enumerate() {
+            output ^= (((row & x).count_ones() & 1) as u8) << i;
+        }
+        output
+    }
+
+    pub fn to_func(&self) -> Func {
+        let mut func = [0; 256];
+        for (x, y) in func.iter_mut().enumerate() {
+            *y = self.apply(x as u8) as i8;
+        }
+        func
+    }
+
+    // Applies the map to the XOR of the two inputs, so the result is still affine in both.
+    pub fn to_func2(&self) -> Box<Func2> {
+        let func = self.to_func();
+
+        let mut func2 = Box::new([[0; 256]; 256]);
+        for (a, row) in func2.iter_mut().enumerate() {
+            for (b, y) in row.iter_mut().enumerate() {
+                *y = func[a ^ b];
+            }
+        }
+        func2
+    }
+
+    // Gaussian elimination, where each row fits in a byte.
+    pub fn rank(&self) -> u32 {
+        let mut rows = self.rows;
+        let mut rank = 0;
+
+        for column in 0..8 {
+            let mask = 1 << column;
+            if let Some(pivot) = (rank..8).find(|&r| rows[r] & mask != 0) {
+                rows.swap(rank, pivot);
+                for r in 0..8 {
+                    if r != rank && rows[r] & mask != 0 {
+                        rows[r] ^= rows[rank];
+                    }
+                }
+                rank += 1;
+            }
+        }
+
+        rank as u32
+    }
+
+    pub fn is_invertible(&self) -> bool {
+        self.rank() == 8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_maps_every_byte_to_itself() {
+        let map = AffineMap::identity();
+
+        for x in 0..=255 {
+            assert_eq!(map.apply(x), x);
+        }
+        assert!(map.is_invertible());
+    }
+
+    #[test]
+    fn toggled_maps_are_affine() {
+        let mut map = AffineMap::identity();
+        map.toggle(3, 5);
+        map.toggle(7, 0);
+        map.toggle_constant(2);
+
+        let func = map.to_func();
+        let f = |x: u8| func[x as usize] as u8;
+
+        for &(x, y) in &[(1, 2), (0x55, 0xaa), (0x0f, 0x3c), (0x80, 0x81)] {
+            assert_eq!(f(x ^ y) ^ f(0), f(x) ^ f(y));
+        }
+        assert_eq!(f(0), 0b100);
+    }
+
+    #[test]
+    fn repeated_rows_are_singular() {
+        let mut map = AffineMap::identity();
+        map.rows[1] = map.rows[0];
+
+        assert_eq!(map.rank(), 7);
+        assert!(!map.is_invertible());
+
+        let func = map.to_func();
+        let mut seen = [false; 256];
+        for &y in func.iter() {
+            seen[y as u8 as usize] = true;
+        }
+        assert_eq!(seen.iter().filter(|&&s| s).count(), 128);
+    }
+}
@@ -0,0 +1,232 @@
+use platform_types::{Button, Input};
+
+pub const MIN_FRAMES_PER_STEP: u8 = 1;
+pub const MAX_FRAMES_PER_STEP: u8 = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axes {
+    X,
+    Y,
+    Both,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Repeat {
+    Loop,
+    PingPong,
+}
+
+// Steps the offsets through their range on its own, so a whole sweep can be watched
+// without holding anything down.
+#[derive(Clone, Copy, Debug)]
+pub struct Animation {
+    pub playing: bool,
+    pub frames_per_step: u8,
+    pub direction: Direction,
+    pub repeat: Repeat,
+    pub axes: Axes,
+    frame_counter: u8,
+    // Set when B was used as a modifier, so releasing it doesn't also toggle playback.
+    settings_changed: bool,
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Animation {
+            playing: false,
+            frames_per_step: 4,
+            direction: Direction::Forward,
+            repeat: Repeat::Loop,
+            axes: Axes::X,
+            frame_counter: 0,
+            settings_changed: false,
+        }
+    }
+}
+
+impl Animation {
+    // Holding B turns the other buttons into the animation settings. Tapping B on its own
+    // toggles playback. The returned input has the buttons used here removed.
+    pub fn update(&mut self, mut input: Input) -> Input {
+        if input.gamepad.contains(Button::B) {
+            if input.pressed_this_frame(Button::B) {
+                self.settings_changed = false;
+            }
+
+            if input.pressed_this_frame(Button::Up) {
+                self.frames_per_step = (self.frames_per_step / 2).max(MIN_FRAMES_PER_STEP);
+                self.settings_changed = true;
+            }
+            if input.pressed_this_frame(Button::Down) {
+                self.frames_per_step = self
+                    .frames_per_step
+                    .saturating_mul(2)
+                    .min(MAX_FRAMES_PER_STEP);
+                self.settings_changed = true;
+            }
+            if input.pressed_this_frame(Button::Left) {
+                self.direction = Direction::Backward;
+                self.settings_changed = true;
+            }
+            if input.pressed_this_frame(Button::Right) {
+                self.direction = Direction::Forward;
+                self.settings_changed = true;
+            }
+            if input.pressed_this_frame(Button::Select) {
+                self.axes = match self.axes {
+                    Axes::X => Axes::Y,
+                    Axes::Y => Axes::Both,
+                    Axes::Both => Axes::X,
+                };
+                self.settings_changed = true;
+            }
+            if input.pressed_this_frame(Button::Start) {
+                self.repeat = match self.repeat {
+                    Repeat::Loop => Repeat::PingPong,
+                    Repeat::PingPong => Repeat::Loop,
+                };
+                self.settings_changed = true;
+            }
+
+            let used = Button::Up
+                | Button::Down
+                | Button::Left
+                | Button::Right
+                | Button::Select
+                | Button::Start;
+            input.gamepad.remove(used);
+            input.previous_gamepad.remove(used);
+        } else if input.released_this_frame(Button::B) && !self.settings_changed {
+            self.playing = !self.playing;
+            self.frame_counter = 0;
+        }
+
+        input
+    }
+
+    pub fn tick(&mut self, x_offset: &mut usize, y_offset: &mut usize) {
+        if !self.playing {
+            return;
+        }
+
+        self.frame_counter += 1;
+        if self.frame_counter < self.frames_per_step {
+            return;
+        }
+        self.frame_counter = 0;
+
+        self.step(x_offset, y_offset);
+    }
+
+    pub fn step(&mut self, x_offset: &mut usize, y_offset: &mut usize) {
+        let (moves_x, moves_y) = match self.axes {
+            Axes::X => (true, false),
+            Axes::Y => (false, true),
+            Axes::Both => (true, true),
+        };
+
+        if self.repeat == Repeat::PingPong {
+            let at_edge = |offset: usize| match self.direction {
+                Direction::Forward => offset >= 255,
+                Direction::Backward => offset == 0,
+            };
+
+            if (moves_x && at_edge(*x_offset)) || (moves_y && at_edge(*y_offset)) {
+                self.direction = match self.direction {
+                    Direction::Forward => Direction::Backward,
+                    Direction::Backward => Direction::Forward,
+                };
+            }
+        }
+
+        let advance = |offset: usize| {
+            let offset = offset as u8;
+            (match self.direction {
+                Direction::Forward => offset.wrapping_add(1),
+                Direction::Backward => offset.wrapping_sub(1),
+            }) as usize
+        };
+
+        if moves_x {
+            *x_offset = advance(*x_offset);
+        }
+        if moves_y {
+            *y_offset = advance(*y_offset);
+        }
+    }
+
+    pub fn status_text(&self) -> String {
+        format!(
+            "{} {}{}f {} {}",
+            if self.playing { "play" } else { "pause" },
+            match self.direction {
+                Direction::Forward => ">",
+                Direction::Backward => "<",
+            },
+            self.frames_per_step,
+            match self.repeat {
+                Repeat::Loop => "loop",
+                Repeat::PingPong => "ping-pong",
+            },
+            match self.axes {
+                Axes::X => "x",
+                Axes::Y => "y",
+                Axes::Both => "x+y",
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looping_wraps_around() {
+        let mut animation = Animation::default();
+        let (mut x, mut y) = (255, 7);
+
+        animation.step(&mut x, &mut y);
+
+        assert_eq!((x, y), (0, 7));
+    }
+
+    #[test]
+    fn ping_pong_turns_around_at_the_edges() {
+        let mut animation = Animation {
+            repeat: Repeat::PingPong,
+            axes: Axes::Both,
+            ..Animation::default()
+        };
+        let (mut x, mut y) = (254, 100);
+
+        animation.step(&mut x, &mut y);
+        assert_eq!((x, y), (255, 101));
+
+        animation.step(&mut x, &mut y);
+        assert_eq!((x, y), (254, 100));
+        assert_eq!(animation.direction, Direction::Backward);
+    }
+
+    #[test]
+    fn tick_only_steps_every_frames_per_step_frames() {
+        let mut animation = Animation {
+            playing: true,
+            frames_per_step: 3,
+            ..Animation::default()
+        };
+        let (mut x, mut y) = (0, 0);
+
+        for _ in 0..9 {
+            animation.tick(&mut x, &mut y);
+        }
+
+        assert_eq!((x, y), (3, 0));
+    }
+}
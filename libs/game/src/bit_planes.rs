@@ -0,0 +1,135 @@
+use crate::game::Func2;
+use rendering::{ColourMap, Framebuffer, BLACK, GREY, WHITE_INDEX};
+
+// Which of the inputs, if any, to show below the output bits for comparison. The rows of
+// the table are `a` and the columns are `b`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputPlanes {
+    #[default]
+    Hidden,
+    Row,
+    Column,
+}
+
+impl InputPlanes {
+    pub const ALL: [InputPlanes; 3] = [InputPlanes::Hidden, InputPlanes::Row, InputPlanes::Column];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            InputPlanes::Hidden => "hidden",
+            InputPlanes::Row => "row (a)",
+            InputPlanes::Column => "column (b)",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Source {
+    Output,
+    Input(InputPlanes),
+}
+
+fn plane_bit(func: &Func2, source: Source, bit: u8, (a, b): (usize, usize)) -> bool {
+    let byte = match source {
+        Source::Output => func[a][b] as u8,
+        Source::Input(InputPlanes::Row) => a as u8,
+        Source::Input(InputPlanes::Column) => b as u8,
+        Source::Input(InputPlanes::Hidden) => 0,
+    };
+
+    byte & (1 << bit) != 0
+}
+
+// Splits `func` into its eight output bits, most significant first. On their own each bit
+// gets a 128 by 64 panel, and with the inputs shown they are 64 by 64 so the input bits
+// fit in the bottom half. Clear bits are the low end of `colour_map` and set bits the
+// high end.
+pub fn render_bit_planes(
+    framebuffer: &mut Framebuffer,
+    func: &Func2,
+    input_planes: InputPlanes,
+    colour_map: &ColourMap,
+) {
+    framebuffer.clear_to(BLACK);
+
+    let output = Source::Output;
+    match input_planes {
+        InputPlanes::Hidden => {
+            render_planes(framebuffer, func, output, colour_map, (0, 0), (128, 64), 2);
+        }
+        InputPlanes::Row | InputPlanes::Column => {
+            render_planes(framebuffer, func, output, colour_map, (0, 0), (64, 64), 4);
+            let input = Source::Input(input_planes);
+            render_planes(framebuffer, func, input, colour_map, (0, 128), (64, 64), 4);
+        }
+    }
+}
+
+fn render_planes(
+    framebuffer: &mut Framebuffer,
+    func: &Func2,
+    source: Source,
+    colour_map: &ColourMap,
+    (left, top): (usize, usize),
+    (width, height): (usize, usize),
+    columns: usize,
+) {
+    let label_prefix = match source {
+        Source::Output => "",
+        Source::Input(InputPlanes::Row) => "a ",
+        Source::Input(_) => "b ",
+    };
+
+    let (clear, set) = (colour_map.colour(0.0), colour_map.colour(1.0));
+    for (index, bit) in (0..8u8).rev().enumerate() {
+        let panel_x = left + (index % columns) * width;
+        let panel_y = top + (index / columns) * height;
+
+        for y in 0..height {
+            for x in 0..width {
+                let cell = (y * 256 / height, x * 256 / width);
+                let i = framebuffer.xy_to_i(panel_x + x, panel_y + y);
+                framebuffer.buffer[i] = if plane_bit(func, source, bit, cell) {
+                    set
+                } else {
+                    clear
+                };
+            }
+        }
+
+        framebuffer.draw_rect(panel_x, panel_y, width, height, GREY);
+        framebuffer.print_line(
+            format!("{}bit {}", label_prefix, bit).as_bytes(),
+            panel_x + 2,
+            panel_y + 2,
+            WHITE_INDEX,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn or_with_the_low_nibble_sets_the_low_planes_and_passes_the_rest_through() {
+        let mut func = Box::new([[0; 256]; 256]);
+        for (a, row) in func.iter_mut().enumerate() {
+            for y in row.iter_mut() {
+                *y = (a as u8 | 0b1111) as i8;
+            }
+        }
+
+        for &cell in &[(0, 0), (0x30, 0x07), (0xa5, 0xff)] {
+            for bit in 0..4 {
+                assert!(plane_bit(&func, Source::Output, bit, cell));
+            }
+            for bit in 4..8 {
+                assert_eq!(
+                    plane_bit(&func, Source::Output, bit, cell),
+                    plane_bit(&func, Source::Input(InputPlanes::Row), bit, cell)
+                );
+            }
+        }
+    }
+}
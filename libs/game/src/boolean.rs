@@ -0,0 +1,185 @@
+use crate::game::Func;
+
+// Turns a truth table into the coefficients of its algebraic normal form, or back again,
+// since the transform is its own inverse. Entry `m` is the coefficient of the monomial
+// made of the input bits set in `m`.
+pub fn mobius_transform(values: &mut [bool; 256]) {
+    let mut bit = 1;
+    while bit < 256 {
+        for x in 0..256 {
+            if x & bit != 0 {
+                values[x] ^= values[x ^ bit];
+            }
+        }
+        bit <<= 1;
+    }
+}
+
+pub fn walsh_hadamard_transform(values: &mut [i16; 256]) {
+    let mut half = 1;
+    while half < 256 {
+        for start in (0..256).step_by(half * 2) {
+            for i in start..start + half {
+                let (u, v) = (values[i], values[i + half]);
+                values[i] = u + v;
+                values[i + half] = u - v;
+            }
+        }
+        half *= 2;
+    }
+}
+
+// One output bit of a `Func`, as a function of the eight input bits.
+pub struct BooleanFunction {
+    pub truth_table: [bool; 256],
+}
+
+impl BooleanFunction {
+    pub fn output_bit(func: &Func, bit: u8) -> Self {
+        let mut truth_table = [false; 256];
+        for (x, value) in truth_table.iter_mut().enumerate() {
+            *value = func[x] as u8 & (1 << bit) != 0;
+        }
+
+        BooleanFunction { truth_table }
+    }
+
+    pub fn weight(&self) -> usize {
+        self.truth_table.iter().filter(|&&value| value).count()
+    }
+
+    pub fn is_balanced(&self) -> bool {
+        self.weight() == 128
+    }
+
+    pub fn anf(&self) -> [bool; 256] {
+        let mut anf = self.truth_table;
+        mobius_transform(&mut anf);
+        anf
+    }
+
+    pub fn degree(&self) -> u32 {
+        self.anf()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &coefficient)| coefficient)
+            .map(|(monomial, _)| monomial.count_ones())
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Entry `a` is how many more inputs agree with the linear function `a & x` than
+    // disagree with it.
+    pub fn walsh_spectrum(&self) -> [i16; 256] {
+        let mut spectrum = [0; 256];
+        for (w, &value) in spectrum.iter_mut().zip(self.truth_table.iter()) {
+            *w = if value { -1 } else { 1 };
+        }
+
+        walsh_hadamard_transform(&mut spectrum);
+
+        spectrum
+    }
+
+    pub fn nonlinearity(&self) -> u16 {
+        let largest = self
+            .walsh_spectrum()
+            .iter()
+            .map(|&w| w.unsigned_abs())
+            .max()
+            .unwrap_or(0);
+
+        128 - largest / 2
+    }
+
+    // Written like `x0x3 + x5 + 1`, with `x0` the least significant input bit.
+    pub fn anf_text(&self) -> String {
+        let anf = self.anf();
+
+        let mut monomials: Vec<usize> = (0..256).filter(|&m| anf[m]).collect();
+        if monomials.is_empty() {
+            return "0".to_string();
+        }
+        // Highest degree first, like polynomials are usually written.
+        monomials.sort_by_key(|&m| (std::cmp::Reverse(m.count_ones()), m));
+
+        let terms: Vec<String> = monomials
+            .into_iter()
+            .map(|m| {
+                if m == 0 {
+                    return "1".to_string();
+                }
+                (0..8)
+                    .filter(|&bit| m & (1 << bit) != 0)
+                    .map(|bit| format!("x{}", bit))
+                    .collect()
+            })
+            .collect();
+
+        terms.join(" + ")
+    }
+}
+
+// The algebraic normal forms of all eight output bits, one per line.
+pub fn anf_report(func: &Func) -> String {
+    let mut report = String::new();
+    for bit in (0..8).rev() {
+        report.push_str(&format!(
+            "y{} = {}\n",
+            bit,
+            BooleanFunction::output_bit(func, bit).anf_text()
+        ));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn func_from<F: Fn(u8) -> u8>(f: F) -> Func {
+        let mut func = [0; 256];
+        for (x, y) in func.iter_mut().enumerate() {
+            *y = f(x as u8) as i8;
+        }
+        func
+    }
+
+    #[test]
+    fn anf_of_and_or_and_not() {
+        let func = func_from(|x| {
+            let (x0, x1) = (x & 1, (x >> 1) & 1);
+            (x0 & x1) | ((x0 | x1) << 1) | ((x0 ^ 1) << 2)
+        });
+
+        let text = |bit| BooleanFunction::output_bit(&func, bit).anf_text();
+        assert_eq!(text(0), "x0x1");
+        assert_eq!(text(1), "x0x1 + x0 + x1");
+        assert_eq!(text(2), "x0 + 1");
+        assert_eq!(text(3), "0");
+    }
+
+    #[test]
+    fn linear_bits_are_balanced_with_a_single_spike_in_the_spectrum() {
+        let func = func_from(|x| (x ^ (x >> 3)) & 1);
+        let function = BooleanFunction::output_bit(&func, 0);
+
+        assert!(function.is_balanced());
+        assert_eq!(function.degree(), 1);
+        assert_eq!(function.nonlinearity(), 0);
+
+        let spectrum = function.walsh_spectrum();
+        assert_eq!(spectrum[0b1001], 256);
+        assert_eq!(spectrum.iter().filter(|&&w| w != 0).count(), 1);
+    }
+
+    #[test]
+    fn mobius_transform_is_its_own_inverse() {
+        let function = BooleanFunction::output_bit(&crate::sbox::AES, 5);
+
+        let mut twice = function.anf();
+        mobius_transform(&mut twice);
+
+        assert_eq!(twice[..], function.truth_table[..]);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::game::Func;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Symmetry {
+    #[default]
+    None,
+    // f(-x) == f(x)
+    Even,
+    // f(-x) == -f(x)
+    Odd,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 3] = [Symmetry::None, Symmetry::Even, Symmetry::Odd];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Symmetry::None => "none",
+            Symmetry::Even => "even",
+            Symmetry::Odd => "odd",
+        }
+    }
+}
+
+#[inline]
+fn get(func: &Func, x: i8) -> i8 {
+    func[x as u8 as usize]
+}
+
+// Sets `func[x] = y`, and the mirrored point too if there is a symmetry to keep.
+// Negation wraps, so -128 is its own mirror image.
+pub fn set_point(func: &mut Func, (x, y): (i8, i8), symmetry: Symmetry) {
+    func[x as u8 as usize] = y;
+
+    let mirrored = x.wrapping_neg() as u8 as usize;
+    match symmetry {
+        Symmetry::None => {}
+        Symmetry::Even => func[mirrored] = y,
+        Symmetry::Odd => func[mirrored] = y.wrapping_neg(),
+    }
+}
+
+// Sets every `x` between the two points, so quick strokes don't leave gaps.
+pub fn paint_line(func: &mut Func, from: (i8, i8), to: (i8, i8), symmetry: Symmetry) {
+    let ((x0, y0), (x1, y1)) = if from.0 <= to.0 {
+        (from, to)
+    } else {
+        (to, from)
+    };
+    let (x0, y0, x1, y1) = (x0 as i32, y0 as i32, x1 as i32, y1 as i32);
+
+    if x0 == x1 {
+        set_point(func, to, symmetry);
+        return;
+    }
+
+    let dx = x1 - x0;
+    for x in x0..=x1 {
+        // Rounds to the nearest, rather than towards zero.
+        let numerator = 2 * (y1 - y0) * (x - x0) + dx;
+        let y = y0 + numerator.div_euclid(2 * dx);
+        set_point(func, (x as i8, y as i8), symmetry);
+    }
+}
+
+// A weighted average of each point with its neighbours, which leaves straight lines alone.
+// The ends only have one neighbour, so they use themselves in place of the missing one.
+pub fn smooth(func: &mut Func) {
+    let original = *func;
+
+    for x in -128i16..=127 {
+        let neighbour = |dx: i16| {
+            let n = (x + dx).clamp(-128, 127) as i8;
+            get(&original, n) as i16
+        };
+
+        let sum = neighbour(-1) + 2 * neighbour(0) + neighbour(1);
+        func[x as i8 as u8 as usize] = (sum + 2).div_euclid(4) as i8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paint_line_fills_in_between_the_ends() {
+        let mut func = [0; 256];
+
+        paint_line(&mut func, (10, 20), (0, 0), Symmetry::None);
+
+        for x in 0..=10 {
+            assert_eq!(get(&func, x), 2 * x);
+        }
+        assert_eq!(get(&func, 11), 0);
+    }
+
+    #[test]
+    fn symmetry_mirrors_points() {
+        let mut func = [0; 256];
+
+        set_point(&mut func, (5, 7), Symmetry::Odd);
+        set_point(&mut func, (9, 3), Symmetry::Even);
+
+        assert_eq!(get(&func, -5), -7);
+        assert_eq!(get(&func, -9), 3);
+    }
+
+    #[test]
+    fn smoothing_flattens_spikes_but_not_lines() {
+        let mut func = [0; 256];
+        for x in -128i16..=127 {
+            func[x as i8 as u8 as usize] = (x / 2) as i8;
+        }
+        let line = func;
+        smooth(&mut func);
+        assert_eq!(func[20..200], line[20..200]);
+
+        let mut func = [0; 256];
+        func[50] = 100;
+        smooth(&mut func);
+        assert_eq!((func[49], func[50], func[51]), (25, 50, 25));
+    }
+}
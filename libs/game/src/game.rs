@@ -1,18 +1,114 @@
+use audio::{note_frequency, note_name};
+use export::{ApngEncoder, GifEncoder, WavSink};
 use features::{GLOBAL_ERROR_LOGGER, GLOBAL_LOGGER};
-use platform_types::{Button, Input, Speaker, State, StateParams, SFX};
-use rand::{Rng, SeedableRng};
+use platform_types::{Button, Command, Export, Input, Pointer, Speaker, State, StateParams, SFX};
+use rand::SeedableRng;
 use rand_xorshift::XorShiftRng;
-use rendering::{Framebuffer, BLACK, BLUE, GREEN, PURPLE, RED, WHITE};
+use rendering::{
+    ColourMap, Framebuffer, Layers, PaletteSet, Ramp, Viewport, BLACK, BLUE, GREEN, RED,
+};
 
+use crate::affine::AffineMap;
+use crate::animation::{Animation, Repeat};
+use crate::bit_planes::{render_bit_planes, InputPlanes};
+use crate::boolean::{anf_report, BooleanFunction};
+use crate::func_editor::{paint_line, smooth, Symmetry};
+use crate::generators::{Func2Generator, FuncGenerator, Generators, MAX_IMAGE_SIZE};
+use crate::history::History;
+use crate::map2d::{Cell, IteratedMap, MapKind};
+use crate::menu::{cycle, Menu, MenuItem};
+use crate::sbox::{library_index, SBoxAnalysis, LIBRARY};
+use crate::sonification::{Sonification, WavetableSource};
+use crate::sounds::{MODE_CHANGE, RANDOMISE};
+use crate::structure::{failures, operate, Property, StructureAnalysis};
+
+use std::error::Error;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum Mode {
+    #[default]
     ViewFunc2,
     VisualizeFunc,
     TestPattern,
+    ViewMap2D,
+    ScrambleImage,
+    DifferenceTable,
+    LinearTable,
+    Structure,
+    AffineEditor,
+    BitPlanes,
+    BooleanFunctions,
+    DrawFunc,
+    Wavetable,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 13] = [
+        Mode::ViewFunc2,
+        Mode::VisualizeFunc,
+        Mode::TestPattern,
+        Mode::ViewMap2D,
+        Mode::ScrambleImage,
+        Mode::DifferenceTable,
+        Mode::LinearTable,
+        Mode::Structure,
+        Mode::AffineEditor,
+        Mode::BitPlanes,
+        Mode::BooleanFunctions,
+        Mode::DrawFunc,
+        Mode::Wavetable,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Mode::ViewFunc2 => "view func2",
+            Mode::VisualizeFunc => "visualize func",
+            Mode::TestPattern => "test pattern",
+            Mode::ViewMap2D => "view 2d map",
+            Mode::ScrambleImage => "scramble image",
+            Mode::DifferenceTable => "difference table",
+            Mode::LinearTable => "linear table",
+            Mode::Structure => "algebraic structure",
+            Mode::AffineEditor => "affine editor",
+            Mode::BitPlanes => "bit planes",
+            Mode::BooleanFunctions => "boolean functions",
+            Mode::DrawFunc => "draw func",
+            Mode::Wavetable => "wavetable",
+        }
+    }
+}
+
+// How the cells in the image of a `Func2` are coloured. With `Polar` and `ReferenceImage`,
+// each input cell gets a colour based on where it is, and the output cells show where they
+// came from. With `Count` the output cells show how many input cells landed on them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Colouring {
+    #[default]
+    Plain,
+    Polar,
+    ReferenceImage,
+    Count,
 }
 
-impl Default for Mode {
-    fn default() -> Mode {
-        Mode::ViewFunc2
+impl Colouring {
+    pub const ALL: [Colouring; 4] = [
+        Colouring::Plain,
+        Colouring::Polar,
+        Colouring::ReferenceImage,
+        Colouring::Count,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Colouring::Plain => "plain",
+            Colouring::Polar => "angle/radius",
+            Colouring::ReferenceImage => "image",
+            Colouring::Count => "count",
+        }
+    }
+
+    pub fn uses_colour_map(self) -> bool {
+        self == Colouring::Polar || self == Colouring::Count
     }
 }
 
@@ -24,6 +120,7 @@ pub type Func = [i8; 256];
 //AKA fn f(a: i8, b: i8) -> i8;
 pub type Func2 = [[i8; 256]; 256];
 
+#[derive(Clone)]
 pub struct GameState {
     pub x_offset: usize,
     pub y_offset: usize,
@@ -32,27 +129,60 @@ pub struct GameState {
     pub func: Func,
     pub func2: Func2,
     pub mode: Mode,
-}
-
-fn randomize_func<R: Rng>(rng: &mut R, func: &mut Func) {
-    rng.fill(func);
-}
-
-fn randomize_func2<R: Rng>(rng: &mut R, func: &mut Func2) {
-    for row in func.iter_mut() {
-        rng.fill(row);
-    }
+    pub viewport: Viewport,
+    pub cursor: (u8, u8),
+    pub animation: Animation,
+    pub colouring: Colouring,
+    pub menu: Menu,
+    pub iterated_map: IteratedMap,
+    pub picture: Vec<u32>,
+    pub is_scrambling: bool,
+    pub sbox_analysis: Option<SBoxAnalysis>,
+    pub property: Property,
+    pub structure_analysis: Option<StructureAnalysis>,
+    pub affine: AffineMap,
+    // The column past the matrix is the constant.
+    pub affine_cursor: (u8, u8),
+    pub input_planes: InputPlanes,
+    pub boolean_bit: u8,
+    pub text_scroll: usize,
+    pub symmetry: Symmetry,
+    // Where the current stroke was on the previous frame, if there is one.
+    pub last_painted: Option<(i8, i8)>,
+    pub history: History,
+    // Frames left to show the history position for, after it changes.
+    pub history_flash: u8,
+    pub generators: Generators,
+    pub sonification: Sonification,
+    pub palette_set: PaletteSet,
+    pub colour_map: ColourMap,
+    // What the ends of the colour map stand for, when the current mode uses it. This is
+    // set again each frame.
+    pub legend: Option<(String, String)>,
+    // Whether a generator filled one of the functions this frame, so the sound for that
+    // only plays when something changed. This is cleared each frame.
+    pub randomised: bool,
+    // How many frames of the animation being exported are done, out of how many, while
+    // there is one.
+    pub export_progress: Option<(usize, usize)>,
+    // Set when something outside of a frame, like undo, changes what the plot shows, so it
+    // is redrawn on the next one. See `plot_needs_redraw`.
+    pub redraw_plot: bool,
 }
 
 impl GameState {
     pub fn new(seed: [u8; 16]) -> GameState {
         let mut rng = XorShiftRng::from_seed(seed);
 
+        let generators = Generators::default();
+
         let mut func = [0; 256];
-        randomize_func(&mut rng, &mut func);
+        generators.fill_func(&mut rng, &mut func);
 
         let mut func2 = [[0; 256]; 256];
-        randomize_func2(&mut rng, &mut func2);
+        generators.fill_func2(&mut rng, &mut func2);
+
+        let history = History::new(&func, &func2);
 
         GameState {
             x_offset: 0,
@@ -62,20 +192,130 @@ impl GameState {
             func,
             func2,
             mode: Default::default(),
+            viewport: Default::default(),
+            cursor: (128, 128),
+            animation: Default::default(),
+            colouring: Default::default(),
+            menu: Default::default(),
+            iterated_map: Default::default(),
+            picture: default_picture(),
+            is_scrambling: true,
+            sbox_analysis: None,
+            property: Default::default(),
+            structure_analysis: None,
+            affine: Default::default(),
+            affine_cursor: (0, 0),
+            input_planes: Default::default(),
+            boolean_bit: 7,
+            text_scroll: 0,
+            symmetry: Default::default(),
+            last_painted: None,
+            history,
+            history_flash: 0,
+            generators,
+            sonification: Default::default(),
+            palette_set: Default::default(),
+            colour_map: Default::default(),
+            legend: None,
+            randomised: false,
+            export_progress: None,
+            redraw_plot: true,
+        }
+    }
+
+    // Accepts a PNG of any size, and scales it to fit the grid.
+    pub fn load_picture(&mut self, png_bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+        use rendering::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+        let (pixels, width, height) = export::decode_png(png_bytes)?;
+        if width == 0 || height == 0 {
+            return Err("The picture is empty".into());
+        }
+        let (width, height) = (width as usize, height as usize);
+
+        let mut picture = Vec::with_capacity(SCREEN_WIDTH * SCREEN_HEIGHT);
+        for y in 0..SCREEN_HEIGHT {
+            for x in 0..SCREEN_WIDTH {
+                let source_x = x * width / SCREEN_WIDTH;
+                let source_y = y * height / SCREEN_HEIGHT;
+                picture.push(pixels[source_y * width + source_x] | 0xFF00_0000);
+            }
+        }
+
+        self.picture = picture;
+        self.redraw_plot = true;
+
+        Ok(())
+    }
+
+    pub fn randomise_func(&mut self) {
+        self.generators.fill_func(&mut self.rng, &mut self.func);
+        self.randomised = true;
+    }
+
+    pub fn randomise_func2(&mut self) {
+        self.generators.fill_func2(&mut self.rng, &mut self.func2);
+        self.randomised = true;
+    }
+
+    pub fn undo(&mut self) {
+        if self.history.undo(&mut self.func, &mut self.func2) {
+            self.history_flash = HISTORY_FLASH_FRAMES;
+            self.redraw_plot = true;
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if self.history.redo(&mut self.func, &mut self.func2) {
+            self.history_flash = HISTORY_FLASH_FRAMES;
+            self.redraw_plot = true;
         }
     }
+
+    pub fn fill_audio(&mut self, sample_rate: u32, samples: &mut [f32]) {
+        let table = self
+            .sonification
+            .table(&self.func, &self.func2, self.x_offset);
+        self.sonification.fill(table, sample_rate, samples);
+    }
+
+    pub fn wavetable_wav(&self) -> Vec<u8> {
+        let table = self
+            .sonification
+            .table(&self.func, &self.func2, self.x_offset);
+        self.sonification.wav(table)
+    }
+}
+
+// The sprite sheet scaled up to cover the whole grid.
+fn default_picture() -> Vec<u32> {
+    use rendering::{sprite_sheet, PALETTE, SCREEN_LENGTH, SCREEN_WIDTH};
+
+    let sheet = sprite_sheet();
+    (0..SCREEN_LENGTH)
+        .map(|i| {
+            let (x, y) = (i % SCREEN_WIDTH, i / SCREEN_WIDTH);
+            PALETTE[sheet.get(x / 2, y / 2) as usize]
+        })
+        .collect()
 }
 
 pub struct EntireState {
     pub game_state: GameState,
     pub framebuffer: Framebuffer,
+    pub layers: Layers,
     pub input: Input,
     pub speaker: Speaker,
+    sweep_export: Option<SweepExport>,
+    finished_export: Option<Result<Export, Box<dyn Error>>>,
 }
 
 impl EntireState {
-    pub fn new((seed, logger, error_logger): StateParams) -> Self {
-        let framebuffer = Framebuffer::new();
+    pub fn new((seed, logger, error_logger, (width, height)): StateParams) -> Self {
+        use rendering::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+        let framebuffer =
+            Framebuffer::with_dimensions(width.max(SCREEN_WIDTH), height.max(SCREEN_HEIGHT));
 
         unsafe {
             GLOBAL_LOGGER = logger;
@@ -85,16 +325,18 @@ impl EntireState {
         EntireState {
             game_state: GameState::new(seed),
             framebuffer,
+            layers: Layers::new(),
             input: Input::new(),
             speaker: Speaker::new(),
+            sweep_export: None,
+            finished_export: None,
         }
     }
-}
 
-impl State for EntireState {
-    fn frame(&mut self, handle_sound: fn(SFX)) {
+    fn update_and_render(&mut self) {
         update_and_render(
             &mut self.framebuffer,
+            &mut self.layers,
             &mut self.game_state,
             self.input,
             &mut self.speaker,
@@ -102,6 +344,56 @@ impl State for EntireState {
 
         self.input.previous_gamepad = self.input.gamepad;
 
+        self.continue_sweep_export();
+    }
+
+    fn start_sweep_export(&mut self, format: AnimationFormat) -> Result<(), Box<dyn Error>> {
+        // Starting again part way through would throw away the frames done so far.
+        if self.sweep_export.is_none() {
+            let sweep = SweepExport::new(&self.game_state, format)?;
+            self.game_state.export_progress = Some(sweep.progress());
+            self.sweep_export = Some(sweep);
+        }
+
+        Ok(())
+    }
+
+    fn continue_sweep_export(&mut self) {
+        // Enough that a sweep takes a few seconds, without making any one frame take long.
+        const FRAMES_PER_FRAME: usize = 4;
+
+        let mut sweep = match self.sweep_export.take() {
+            Some(sweep) => sweep,
+            None => return,
+        };
+
+        let result = match sweep.render_frames(FRAMES_PER_FRAME) {
+            Ok(false) => {
+                self.game_state.export_progress = Some(sweep.progress());
+                self.sweep_export = Some(sweep);
+                return;
+            }
+            Ok(true) => sweep.finish(),
+            Err(error) => Err(error),
+        };
+
+        self.game_state.export_progress = None;
+        self.finished_export = Some(result);
+    }
+
+    // Like `State::frame`, but the sounds go to `sink` rather than being played, for
+    // checking what a sequence of inputs sounds like without a browser.
+    pub fn headless_frame(&mut self, sink: &mut WavSink) {
+        self.update_and_render();
+
+        sink.frame(self.speaker.drain());
+    }
+}
+
+impl State for EntireState {
+    fn frame(&mut self, handle_sound: fn(SFX)) {
+        self.update_and_render();
+
         for request in self.speaker.drain() {
             handle_sound(request);
         }
@@ -121,9 +413,209 @@ impl State for EntireState {
         self.input.gamepad.remove(button);
     }
 
+    fn move_pointer(&mut self, pointer: Option<Pointer>) {
+        self.input.pointer = pointer;
+    }
+
     fn get_frame_buffer(&self) -> &[u32] {
         &self.framebuffer.buffer
     }
+
+    fn get_frame_buffer_dimensions(&self) -> (usize, usize) {
+        (self.framebuffer.width, self.framebuffer.height)
+    }
+
+    // U and Y undo and redo, and G and N start exporting the animation as a GIF or an
+    // APNG, which comes out of `finished_export` a few seconds later. Files with "font" or
+    // "gfx" in their names replace the font or the sprite sheet, which must be the size of
+    // the ones they replace and only use `rendering::PALETTE`, or black and white for the
+    // font. Any other file replaces the picture.
+    fn handle_command(&mut self, command: Command) -> Result<bool, Box<dyn Error>> {
+        match command {
+            Command::Key("u") => self.game_state.undo(),
+            Command::Key("y") => self.game_state.redo(),
+            Command::Key("g") => self.start_sweep_export(AnimationFormat::Gif)?,
+            Command::Key("n") => self.start_sweep_export(AnimationFormat::Apng)?,
+            Command::Key(_) => return Ok(false),
+            Command::File { name, bytes } => {
+                let name = name.to_lowercase();
+                let (what, result) = if name.contains("font") {
+                    ("font", rendering::load_font(bytes))
+                } else if name.contains("gfx") {
+                    ("sprite sheet", rendering::load_sprite_sheet(bytes))
+                } else {
+                    ("picture", self.game_state.load_picture(bytes))
+                };
+
+                if let Err(error) = result {
+                    return Err(format!("Couldn't load the {}: {}", what, error).into());
+                }
+
+                // Some modes print on the plot, so a new font changes it too.
+                self.game_state.redraw_plot = true;
+            }
+        }
+
+        Ok(true)
+    }
+
+    // W exports the wavetable as a WAV, and T the algebraic normal form of each output bit
+    // as text.
+    fn export(&mut self, key: &str) -> Option<Result<Export, Box<dyn Error>>> {
+        let export = |file_name, mime_type, bytes| Export {
+            file_name,
+            mime_type,
+            bytes,
+        };
+
+        Some(match key {
+            "w" => Ok(export(
+                "256_squared.wav",
+                "audio/wav",
+                self.game_state.wavetable_wav(),
+            )),
+            "t" => Ok(export(
+                "256_squared_anf.txt",
+                "text/plain",
+                anf_report(&self.game_state.func).into_bytes(),
+            )),
+            _ => return None,
+        })
+    }
+
+    fn finished_export(&mut self) -> Option<Result<Export, Box<dyn Error>>> {
+        self.finished_export.take()
+    }
+
+    fn fill_audio(&mut self, sample_rate: u32, samples: &mut [f32]) {
+        self.game_state.fill_audio(sample_rate, samples);
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+}
+
+enum SweepEncoder {
+    Gif(GifEncoder),
+    Apng(ApngEncoder),
+}
+
+// One full sweep of the offsets, following the current animation settings, rendered and
+// encoded a few frames at a time so the page can keep drawing in between. Only the plot is
+// in the frames, without the menu or anything else over it. Some modes change the state as
+// they render, like stepping the scrambled image, so this works on a copy of it.
+pub struct SweepExport {
+    state: GameState,
+    animation: Animation,
+    format: AnimationFormat,
+    encoder: SweepEncoder,
+    plot: Framebuffer,
+    frames_done: usize,
+    frame_count: usize,
+}
+
+impl SweepExport {
+    pub fn new(state: &GameState, format: AnimationFormat) -> Result<Self, Box<dyn Error>> {
+        use rendering::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+        let mut state = state.clone();
+
+        let mut animation = state.animation;
+        animation.playing = false;
+        state.animation = animation;
+
+        let frame_count = match animation.repeat {
+            Repeat::Loop => 256,
+            Repeat::PingPong => 2 * 255,
+        };
+        // The animation steps at most once a frame, and we assume 60 frames per second.
+        let frames_per_step = animation.frames_per_step as u16;
+
+        let encoder = match format {
+            // The frames come out in the screen palette's colours, so the GIF's matches it.
+            AnimationFormat::Gif => SweepEncoder::Gif(GifEncoder::new(
+                SCREEN_WIDTH as u16,
+                SCREEN_HEIGHT as u16,
+                &state.palette_set.colours(),
+                (frames_per_step * 100 / 60).max(2),
+            )?),
+            AnimationFormat::Apng => SweepEncoder::Apng(ApngEncoder::new(
+                frame_count as u32,
+                SCREEN_WIDTH as u32,
+                SCREEN_HEIGHT as u32,
+                (frames_per_step, 60),
+            )?),
+        };
+
+        Ok(SweepExport {
+            state,
+            animation,
+            format,
+            encoder,
+            plot: Framebuffer::new(),
+            frames_done: 0,
+            frame_count,
+        })
+    }
+
+    // Renders and encodes up to `count` more frames. Returns whether they are all done.
+    pub fn render_frames(&mut self, count: usize) -> Result<bool, Box<dyn Error>> {
+        let end = self.frame_count.min(self.frames_done.saturating_add(count));
+
+        while self.frames_done < end {
+            let state = &mut self.state;
+            update_and_render_plot(&mut self.plot, state, Input::new());
+            self.animation
+                .step(&mut state.x_offset, &mut state.y_offset);
+
+            // The plot is drawn over on the next frame, so the palette is swapped in a copy.
+            let mut frame = self.plot.clone();
+            frame.screen_palette = state.palette_set.colours();
+            frame.apply_screen_palette();
+
+            match self.encoder {
+                SweepEncoder::Gif(ref mut encoder) => encoder.write_frame(&frame.buffer)?,
+                SweepEncoder::Apng(ref mut encoder) => encoder.write_frame(&frame.buffer)?,
+            }
+
+            self.frames_done += 1;
+        }
+
+        Ok(self.frames_done == self.frame_count)
+    }
+
+    pub fn progress(&self) -> (usize, usize) {
+        (self.frames_done, self.frame_count)
+    }
+
+    pub fn finish(self) -> Result<Export, Box<dyn Error>> {
+        let (file_name, mime_type) = match self.format {
+            AnimationFormat::Gif => ("256_squared.gif", "image/gif"),
+            AnimationFormat::Apng => ("256_squared.png", "image/apng"),
+        };
+
+        let bytes = match self.encoder {
+            SweepEncoder::Gif(encoder) => encoder.finish()?,
+            SweepEncoder::Apng(encoder) => encoder.finish()?,
+        };
+
+        Ok(Export {
+            file_name,
+            mime_type,
+            bytes,
+        })
+    }
+}
+
+// Does the whole of a `SweepExport` at once.
+pub fn export_sweep(state: &GameState, format: AnimationFormat) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut sweep = SweepExport::new(state, format)?;
+    sweep.render_frames(usize::MAX)?;
+
+    Ok(sweep.finish()?.bytes)
 }
 
 fn checkerboard_pattern(framebuffer: &mut Framebuffer, state: &mut GameState) {
@@ -168,6 +660,12 @@ fn xy_to_i((x, y): (i8, i8)) -> usize {
     ((((1 << 8) - y_corner as i16) as u8 as usize) << 8) | x_corner as u8 as usize
 }
 
+// The cursor is a position on the grid, which has a row of 256 cells for each row of
+// pixels, so this is the same index as the plot uses.
+fn cursor_to_i((x, y): (u8, u8)) -> usize {
+    (y as usize) << 8 | x as usize
+}
+
 fn i_to_xy(i: usize) -> (i8, i8) {
     let (x_corner, y_corner) = ((i & 0b1111_1111) as i8, ((1 << 8) - (i >> 8)) as i8);
 
@@ -189,6 +687,364 @@ mod test {
             xy == i_to_xy(xy_to_i(xy))
         }
     }
+
+    #[test]
+    fn export_sweep_leaves_the_state_as_it_was() {
+        let mut state = GameState::new([42; 16]);
+        state.x_offset = 17;
+        state.y_offset = 3;
+        state.mode = Mode::ScrambleImage;
+        state.history_flash = 5;
+
+        let bytes = export_sweep(&state, AnimationFormat::Gif).unwrap();
+
+        assert!(bytes.starts_with(b"GIF89a"));
+        assert_eq!((state.x_offset, state.y_offset), (17, 3));
+        assert_eq!(state.iterated_map.iterations, 0);
+        assert_eq!(state.history_flash, 5);
+        assert!(!state.animation.playing);
+    }
+
+    #[test]
+    fn export_sweep_frames_only_show_the_plot() {
+        let mut state = GameState::new([42; 16]);
+        state.menu.open = true;
+        state.history_flash = 5;
+
+        let bytes = export_sweep(&state, AnimationFormat::Apng).unwrap();
+
+        let mut plot = Framebuffer::new();
+        update_and_render_plot(&mut plot, &mut state, Input::new());
+        let (first_frame, _, _) = export::decode_png(&bytes).unwrap();
+        assert!(first_frame == plot.buffer);
+    }
+
+    #[test]
+    fn stepping_the_scrambled_image_by_hand_pauses_it() {
+        let mut state = GameState::new([42; 16]);
+        let mut framebuffer = Framebuffer::new();
+
+        let mut input = Input::new();
+        update_and_render_scramble_image(&mut framebuffer, &mut state, input);
+        update_and_render_scramble_image(&mut framebuffer, &mut state, input);
+        assert_eq!(state.iterated_map.iterations, 2);
+
+        input.gamepad = Button::Left;
+        update_and_render_scramble_image(&mut framebuffer, &mut state, input);
+        assert_eq!(state.iterated_map.iterations, 1);
+        assert!(!state.is_scrambling);
+
+        input.previous_gamepad = input.gamepad;
+        update_and_render_scramble_image(&mut framebuffer, &mut state, input);
+        assert_eq!(state.iterated_map.iterations, 1);
+    }
+
+    #[test]
+    fn b_and_select_switch_the_animated_axes_without_opening_the_menu() {
+        use crate::animation::Axes;
+
+        let mut state = GameState::new([42; 16]);
+        state.mode = Mode::ViewFunc2;
+        let mut framebuffer = Framebuffer::new();
+        let mut layers = Layers::new();
+        let mut speaker = Speaker::new();
+
+        let mut input = Input::new();
+        input.gamepad = Button::B;
+        update_and_render(
+            &mut framebuffer,
+            &mut layers,
+            &mut state,
+            input,
+            &mut speaker,
+        );
+
+        input.previous_gamepad = input.gamepad;
+        input.gamepad = Button::B | Button::Select;
+        update_and_render(
+            &mut framebuffer,
+            &mut layers,
+            &mut state,
+            input,
+            &mut speaker,
+        );
+
+        assert!(!state.menu.open);
+        assert_eq!(state.animation.axes, Axes::Y);
+    }
+
+    #[test]
+    fn changing_mode_and_randomising_make_sounds() {
+        let mut state = GameState::new([42; 16]);
+        let mut framebuffer = Framebuffer::new();
+        let mut layers = Layers::new();
+        let mut speaker = Speaker::new();
+
+        let mut frame = |state: &mut GameState, buttons| {
+            let mut input = Input::new();
+            input.gamepad = buttons;
+            update_and_render(&mut framebuffer, &mut layers, state, input, &mut speaker);
+            speaker.drain().collect::<Vec<_>>()
+        };
+
+        assert_eq!(frame(&mut state, Button::A), vec![MODE_CHANGE]);
+        assert_eq!(frame(&mut state, Button::Start), vec![RANDOMISE]);
+
+        state.mode = Mode::DrawFunc;
+        assert_eq!(frame(&mut state, Button::Start), vec![]);
+    }
+
+    #[test]
+    fn b_and_start_change_the_repeat_mode_without_the_randomise_sound() {
+        let mut state = GameState::new([42; 16]);
+        state.mode = Mode::ViewFunc2;
+        let func2 = state.func2;
+        let mut framebuffer = Framebuffer::new();
+        let mut layers = Layers::new();
+        let mut speaker = Speaker::new();
+
+        let mut input = Input::new();
+        input.gamepad = Button::B;
+        update_and_render(
+            &mut framebuffer,
+            &mut layers,
+            &mut state,
+            input,
+            &mut speaker,
+        );
+
+        input.previous_gamepad = input.gamepad;
+        input.gamepad = Button::B | Button::Start;
+        update_and_render(
+            &mut framebuffer,
+            &mut layers,
+            &mut state,
+            input,
+            &mut speaker,
+        );
+
+        assert_eq!(state.animation.repeat, Repeat::PingPong);
+        assert!(state.func2[..] == func2[..]);
+        assert_eq!(speaker.drain().count(), 0);
+    }
+
+    #[test]
+    fn replays_sound_the_same_every_time() {
+        use audio::{DEFAULT_SAMPLE_RATE, FRAME_RATE};
+
+        let replay = || {
+            let mut state = EntireState::new(([42; 16], None, None, (256, 256)));
+            let mut sink = WavSink::new(DEFAULT_SAMPLE_RATE);
+
+            for frame in 0..30 {
+                match frame {
+                    5 => state.press(Button::A),
+                    6 => state.release(Button::A),
+                    20 => state.press(Button::Start),
+                    _ => {}
+                }
+                state.headless_frame(&mut sink);
+            }
+            sink.finish();
+
+            sink
+        };
+
+        let sink = replay();
+        let frame_length = (DEFAULT_SAMPLE_RATE / FRAME_RATE) as usize;
+        let frame = |n: usize| &sink.samples[n * frame_length..(n + 1) * frame_length];
+
+        assert!(frame(4).iter().all(|&sample| sample == 0.0));
+        assert!(frame(5).iter().any(|&sample| sample != 0.0));
+        assert!(frame(20).iter().any(|&sample| sample != 0.0));
+        assert_eq!(sink.wav(), replay().wav());
+    }
+
+    #[test]
+    fn the_plot_is_only_redrawn_when_something_changes_it() {
+        let mut state = GameState::new([42; 16]);
+        let mut framebuffer = Framebuffer::new();
+        let mut layers = Layers::new();
+        let mut speaker = Speaker::new();
+
+        let mut frame = |state: &mut GameState, layers: &mut Layers, input: Input| {
+            update_and_render(&mut framebuffer, layers, state, input, &mut speaker);
+        };
+
+        frame(&mut state, &mut layers, Input::new());
+        let plot = layers.plot.buffer.clone();
+
+        // Zooming in is done when compositing, so it doesn't get into the plot.
+        state.viewport.zoom_in((0, 0));
+        layers.plot.buffer[0] = RED;
+        frame(&mut state, &mut layers, Input::new());
+        assert_eq!(layers.plot.buffer[0], RED);
+
+        let mut input = Input::new();
+        input.gamepad = Button::Y;
+        frame(&mut state, &mut layers, input);
+        assert!(layers.plot.buffer == plot);
+
+        layers.plot.buffer[0] = RED;
+        let png = export::encode_png(&[BLUE; 4], 2, 2).unwrap();
+        state.load_picture(&png).unwrap();
+        frame(&mut state, &mut layers, Input::new());
+        assert!(layers.plot.buffer == plot);
+    }
+
+    #[test]
+    fn modes_that_use_the_colour_map_show_a_legend() {
+        let mut state = GameState::new([42; 16]);
+        let mut framebuffer = Framebuffer::new();
+        let mut layers = Layers::new();
+        let mut speaker = Speaker::new();
+
+        // The settings are changed directly rather than through the menu, so the plot needs
+        // telling to redraw.
+        let mut legend = |state: &mut GameState| {
+            state.redraw_plot = true;
+            update_and_render(
+                &mut framebuffer,
+                &mut layers,
+                state,
+                Input::new(),
+                &mut speaker,
+            );
+            state.legend.clone()
+        };
+
+        assert_eq!(legend(&mut state), None);
+
+        state.colouring = Colouring::Count;
+        let (low, high) = legend(&mut state).unwrap();
+        assert_eq!(low, "0");
+        assert!(high.parse::<u32>().unwrap() >= 1);
+
+        state.colouring = Colouring::Polar;
+        assert_eq!(legend(&mut state).unwrap().0, "-180");
+
+        state.mode = Mode::LinearTable;
+        assert!(legend(&mut state).unwrap().0.starts_with('-'));
+
+        state.mode = Mode::BitPlanes;
+        let ends = Some(("0".to_string(), "1".to_string()));
+        assert_eq!(legend(&mut state), ends);
+    }
+
+    #[test]
+    fn the_two_colour_modes_use_the_ends_of_the_colour_map() {
+        let mut state = GameState::new([42; 16]);
+        state.colour_map = ColourMap {
+            ramp: Ramp::Greyscale,
+            quantised: false,
+        };
+        let ends = [state.colour_map.colour(0.0), state.colour_map.colour(1.0)];
+
+        for &mode in &[Mode::Structure, Mode::BitPlanes] {
+            state.mode = mode;
+            let mut plot = Framebuffer::new();
+            update_and_render_plot(&mut plot, &mut state, Input::new());
+
+            let on_the_map = plot.buffer.iter().filter(|c| ends.contains(c)).count();
+            assert!(on_the_map > plot.buffer.len() / 2, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn wider_framebuffers_keep_the_grid_and_add_a_panel() {
+        use rendering::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+        let mut state = EntireState::new(([42; 16], None, None, (512, 100)));
+        state.frame(|_| {});
+
+        assert_eq!(state.get_frame_buffer_dimensions(), (512, SCREEN_HEIGHT));
+        assert_eq!(state.get_frame_buffer().len(), 512 * SCREEN_HEIGHT);
+
+        let framebuffer = &state.framebuffer;
+        let panel = &framebuffer.buffer[SCREEN_WIDTH..framebuffer.width];
+        assert!(panel.iter().all(|&colour| colour == BLACK));
+        let text = (0..SCREEN_HEIGHT)
+            .flat_map(|y| (SCREEN_WIDTH..framebuffer.width).map(move |x| (x, y)))
+            .any(|(x, y)| framebuffer.buffer[framebuffer.index(x, y).unwrap()] != BLACK);
+        assert!(text);
+    }
+
+    #[test]
+    fn commands_and_exports_are_picked_by_key() {
+        let mut state = EntireState::new(([42; 16], None, None, (256, 256)));
+
+        assert!(state.handle_command(Command::Key("u")).unwrap());
+        assert!(!state.handle_command(Command::Key("z")).unwrap());
+        assert!(state.export("z").is_none());
+
+        let export = state.export("t").unwrap().unwrap();
+        assert_eq!(export.mime_type, "text/plain");
+        assert!(!export.bytes.is_empty());
+
+        let file = Command::File {
+            name: "my_gfx.png",
+            bytes: b"not a PNG",
+        };
+        let error = state.handle_command(file).unwrap_err();
+        assert!(error.to_string().contains("sprite sheet"));
+    }
+
+    #[test]
+    fn animation_exports_are_made_over_several_frames() {
+        let mut state = EntireState::new(([42; 16], None, None, (256, 256)));
+
+        assert!(state.export("g").is_none());
+        assert!(state.handle_command(Command::Key("g")).unwrap());
+        assert_eq!(state.game_state.export_progress, Some((0, 256)));
+
+        state.update_and_render();
+        assert!(state.finished_export().is_none());
+        assert_eq!(state.game_state.export_progress, Some((4, 256)));
+
+        let mut frames = 1;
+        let export = loop {
+            state.update_and_render();
+            frames += 1;
+
+            if let Some(result) = state.finished_export() {
+                break result.unwrap();
+            }
+        };
+
+        assert_eq!(frames, 256 / 4);
+        assert_eq!(export.mime_type, "image/gif");
+        assert!(export.bytes.starts_with(b"GIF89a"));
+        assert!(state.game_state.export_progress.is_none());
+    }
+
+    #[test]
+    fn only_pointers_over_the_grid_paint() {
+        use rendering::SCREEN_WIDTH;
+
+        let mut state = GameState::new([42; 16]);
+        let (before, cursor) = (state.func, state.cursor);
+
+        let mut input = Input::new();
+        input.pointer = Some(Pointer {
+            x: SCREEN_WIDTH + 10,
+            y: 10,
+            pressed: true,
+        });
+        update_func_editor(&mut state, input);
+
+        assert_eq!(state.cursor, cursor);
+        assert_eq!(&state.func[..], &before[..]);
+
+        input.pointer = Some(Pointer {
+            x: 10,
+            y: 200,
+            pressed: true,
+        });
+        update_func_editor(&mut state, input);
+
+        assert_eq!(state.cursor, (10, 200));
+        assert_ne!(&state.func[..], &before[..]);
+    }
 }
 
 fn update_and_render_test_pattern(
@@ -209,43 +1065,124 @@ fn update_and_render_test_pattern(
     }
 }
 
-fn apply_func2(framebuffer: &mut Framebuffer, state: &mut GameState) {
-    framebuffer.clear_to(RED);
+// The angle goes around the colour map, and cells further out are brighter. The cyclic
+// ramp is the one that doesn't leave a seam on the left.
+fn polar_colour(colour_map: &ColourMap, (x, y): (i8, i8)) -> u32 {
+    use std::f32::consts::PI;
 
-    let func = &state.func2;
+    let (x, y) = (x as f32 + 0.5, y as f32 + 0.5);
+    let angle = (y.atan2(x) + PI) / (2.0 * PI);
+    let radius = (x * x + y * y).sqrt() / (128.0 * std::f32::consts::SQRT_2);
+
+    colour_map.shaded(angle, 0.25 + 0.75 * radius)
+}
+
+fn provenance_colour(
+    colouring: Colouring,
+    colour_map: &ColourMap,
+    picture: &[u32],
+    i: usize,
+) -> u32 {
+    match colouring {
+        Colouring::Plain | Colouring::Count => BLUE,
+        Colouring::Polar => polar_colour(colour_map, i_to_xy(i)),
+        Colouring::ReferenceImage => picture[i],
+    }
+}
+
+// Draws the image of the whole grid under a map given as a function from each framebuffer
+// index to the index it lands on. With provenance colouring each output cell ends up as
+// the average colour of all the input cells that map to it. Returns the legend for the
+// colour map, if it was used.
+fn render_image<F: Fn(usize) -> usize>(
+    framebuffer: &mut Framebuffer,
+    colouring: Colouring,
+    colour_map: &ColourMap,
+    picture: &[u32],
+    target_of: F,
+) -> Option<(String, String)> {
+    if colouring == Colouring::Plain {
+        framebuffer.clear_to(RED);
+
+        for i in 0..(256 * 256) {
+            framebuffer.buffer[target_of(i)] = BLUE;
+        }
+
+        return None;
+    }
+
+    if colouring == Colouring::Count {
+        let mut counts = vec![0u32; 256 * 256];
+        for i in 0..(256 * 256) {
+            counts[target_of(i)] += 1;
+        }
+
+        let max = counts.iter().cloned().max().unwrap_or(0).max(1);
+        for (pixel, &count) in framebuffer.buffer.iter_mut().zip(counts.iter()) {
+            *pixel = colour_map.colour(count as f32 / max as f32);
+        }
+
+        return Some(("0".to_string(), max.to_string()));
+    }
+
+    framebuffer.clear_to(BLACK);
+
+    let mut counts = vec![0u32; 256 * 256];
 
     for i in 0..(256 * 256) {
-        let (mut x, mut y) = i_to_xy(i);
+        let target = target_of(i);
 
-        x = func[state.x_offset][x as u8 as usize];
-        y = func[state.y_offset][y as u8 as usize];
+        counts[target] += 1;
+        let alpha = 255 / counts[target];
 
-        let i = xy_to_i((x, y));
+        let colour = provenance_colour(colouring, colour_map, picture, i);
+        framebuffer.blend(target, (colour & 0x00FF_FFFF) | alpha << 24);
+    }
 
-        framebuffer.buffer[i] = BLUE;
+    // The angle, in degrees, starting from the left.
+    if colouring == Colouring::Polar {
+        Some(("-180".to_string(), "180".to_string()))
+    } else {
+        None
     }
 }
 
+fn apply_func2(framebuffer: &mut Framebuffer, state: &mut GameState) {
+    let func = &state.func2;
+    let (x_offset, y_offset) = (state.x_offset, state.y_offset);
+
+    state.legend = render_image(
+        framebuffer,
+        state.colouring,
+        &state.colour_map,
+        &state.picture,
+        |i| {
+            let (mut x, mut y) = i_to_xy(i);
+
+            x = func[x_offset][x as u8 as usize];
+            y = func[y_offset][y as u8 as usize];
+
+            xy_to_i((x, y))
+        },
+    );
+}
+
 fn update_and_render_view_func2(
     framebuffer: &mut Framebuffer,
     state: &mut GameState,
     input: Input,
 ) {
-    let (left, right, up, down) = if input.gamepad.contains(Button::B) {
-        (
-            input.gamepad.contains(Button::Left),
-            input.gamepad.contains(Button::Right),
-            input.gamepad.contains(Button::Up),
-            input.gamepad.contains(Button::Down),
-        )
-    } else {
-        (
-            input.pressed_this_frame(Button::Left),
-            input.pressed_this_frame(Button::Right),
-            input.pressed_this_frame(Button::Up),
-            input.pressed_this_frame(Button::Down),
-        )
-    };
+    let input = state.animation.update(input);
+    state
+        .animation
+        .tick(&mut state.x_offset, &mut state.y_offset);
+
+    let (left, right, up, down) = (
+        input.pressed_this_frame(Button::Left),
+        input.pressed_this_frame(Button::Right),
+        input.pressed_this_frame(Button::Up),
+        input.pressed_this_frame(Button::Down),
+    );
 
     if right {
         state.x_offset = (state.x_offset as u8).saturating_add(1) as _;
@@ -261,9 +1198,8 @@ fn update_and_render_view_func2(
     }
 
     match input.gamepad {
-        Button::Select => framebuffer.clear_to(WHITE),
         Button::Start => {
-            randomize_func2(&mut state.rng, &mut state.func2);
+            state.randomise_func2();
             framebuffer.clear_to(GREEN)
         }
         _ => {
@@ -292,9 +1228,8 @@ fn update_and_render_visualize_func(
     input: Input,
 ) {
     match input.gamepad {
-        Button::Select => framebuffer.clear_to(WHITE),
         Button::Start => {
-            randomize_func(&mut state.rng, &mut state.func);
+            state.randomise_func();
             framebuffer.clear_to(GREEN)
         }
         _ => {
@@ -303,30 +1238,1396 @@ fn update_and_render_visualize_func(
     }
 }
 
-#[inline]
-pub fn update_and_render(
+// Both tables are too slow to work out every frame, so this only does it when `func` has
+// changed since last time.
+fn sbox_analysis(state: &mut GameState) -> &SBoxAnalysis {
+    let func = &state.func;
+    match state.sbox_analysis {
+        Some(ref analysis) if analysis.is_for(func) => {}
+        _ => state.sbox_analysis = Some(SBoxAnalysis::new(func)),
+    }
+
+    state.sbox_analysis.as_ref().unwrap()
+}
+
+// The rows are the input differences or masks, and the columns are the output ones.
+fn update_and_render_sbox_table(
     framebuffer: &mut Framebuffer,
     state: &mut GameState,
     input: Input,
-    _speaker: &mut Speaker,
 ) {
+    use rendering::signed_fraction;
+
+    if input.pressed_this_frame(Button::Start) {
+        state.randomise_func();
+    }
+
+    let mode = state.mode;
+    let colour_map = state.colour_map;
+    let analysis = sbox_analysis(state);
+
+    match mode {
+        Mode::LinearTable => {
+            // Entries are never further from 0 than this outside of the trivial one.
+            let scale = (128 - analysis.nonlinearity).max(1) as i32;
+            for (a, row) in analysis.lat.iter().enumerate() {
+                for (b, &bias) in row.iter().enumerate() {
+                    framebuffer.buffer[a * 256 + b] =
+                        colour_map.colour(signed_fraction(bias as i32, scale));
+                }
+            }
+
+            state.legend = Some((format!("-{}", scale), format!("+{}", scale)));
+        }
+        _ => {
+            let scale = analysis.differential_uniformity.max(1) as u32;
+            for (a, row) in analysis.ddt.iter().enumerate() {
+                for (b, &count) in row.iter().enumerate() {
+                    framebuffer.buffer[a * 256 + b] =
+                        colour_map.colour(count as f32 / scale as f32);
+                }
+            }
+
+            state.legend = Some(("0".to_string(), scale.to_string()));
+        }
+    }
+}
+
+fn render_sbox_status(framebuffer: &mut Framebuffer, state: &GameState) {
+    use rendering::WHITE_INDEX;
+
+    let analysis = match state.sbox_analysis {
+        Some(ref analysis) => analysis,
+        None => return,
+    };
+
+    let (b, a) = (state.cursor.0 as usize, state.cursor.1 as usize);
+    let entry = match state.mode {
+        Mode::LinearTable => format!("lat[{:02x}][{:02x}] = {}", a, b, analysis.lat[a][b]),
+        _ => format!("ddt[{:02x}][{:02x}] = {}", a, b, analysis.ddt[a][b]),
+    };
+
+    let lines = [
+        entry,
+        format!(
+            "{} du {} nl {} deg {}",
+            sbox_name(&state.func),
+            analysis.differential_uniformity,
+            analysis.nonlinearity,
+            analysis.algebraic_degree
+        ),
+    ];
+
+    print_lines_at_bottom(framebuffer, &lines, WHITE_INDEX);
+}
+
+// Checking associativity fully means trying every triple, so like the S-box analysis this
+// is only redone when `func2` has changed.
+fn structure_analysis(state: &mut GameState) -> &StructureAnalysis {
+    let func = &state.func2;
+    match state.structure_analysis {
+        Some(ref analysis) if analysis.is_for(func) => {}
+        _ => state.structure_analysis = Some(StructureAnalysis::new(func)),
+    }
+
+    state.structure_analysis.as_ref().unwrap()
+}
+
+// Shows the cells that break the chosen property in the high end of the colour map, with
+// row `a` and column `b`. Associativity needs a third element, which Left and Right
+// choose. There is no legend, since it would cover the status text.
+fn update_and_render_structure(framebuffer: &mut Framebuffer, state: &mut GameState, input: Input) {
+    if input.pressed_this_frame(Button::Start) {
+        state.randomise_func2();
+    }
+    if input.pressed_this_frame(Button::Right) {
+        state.x_offset = (state.x_offset as u8).wrapping_add(1) as _;
+    }
+    if input.pressed_this_frame(Button::Left) {
+        state.x_offset = (state.x_offset as u8).wrapping_sub(1) as _;
+    }
+
+    structure_analysis(state);
+
+    let failures = failures(&state.func2, state.property, state.x_offset as u8);
+    let (holds_colour, fails_colour) = (state.colour_map.colour(0.0), state.colour_map.colour(1.0));
+    for (pixel, &fails) in framebuffer.buffer.iter_mut().zip(failures.iter()) {
+        *pixel = if fails { fails_colour } else { holds_colour };
+    }
+}
+
+fn render_structure_status(framebuffer: &mut Framebuffer, state: &GameState) {
+    use rendering::WHITE_INDEX;
+
+    let analysis = match state.structure_analysis {
+        Some(ref analysis) => analysis,
+        None => return,
+    };
+
+    let hex_or_none = |element: Option<u8>| match element {
+        Some(element) => format!("{:02x}", element),
+        None => "none".to_string(),
+    };
+
+    let (b, a) = state.cursor;
+    let c = state.x_offset as u8;
+    let mut entry = format!(
+        "{} {:02x} * {:02x} = {:02x}",
+        state.property.name(),
+        a,
+        b,
+        operate(&state.func2, a, b)
+    );
+    if state.property == Property::Associativity {
+        entry.push_str(&format!(" c = {:02x}", c));
+    }
+
+    let commutativity = match analysis.commutativity_counterexample {
+        Some((a, b)) => format!("{:02x} * {:02x} != {:02x} * {:02x}", a, b, b, a),
+        None => "commutative".to_string(),
+    };
+    let associativity = match analysis.associativity_counterexample {
+        Some((a, b, c)) => format!("({:02x} * {:02x}) * {:02x} differs", a, b, c),
+        None => "associative".to_string(),
+    };
+
+    let lines = [
+        entry,
+        format!(
+            "{}: {}, {}",
+            analysis.classification(),
+            commutativity,
+            associativity
+        ),
+        format!(
+            "identity {} absorbing {} inverses {} idempotents {}",
+            hex_or_none(analysis.identity),
+            hex_or_none(analysis.absorbing),
+            analysis.invertible_count,
+            analysis.idempotent_count
+        ),
+    ];
+
+    print_lines_at_bottom(framebuffer, &lines, WHITE_INDEX);
+}
+
+// The matrix is shown with the most significant bits first, so reading along a row gives
+// the input bits that output bit goes by, the way binary is usually written.
+fn affine_cursor_bits(state: &GameState) -> (usize, Option<usize>) {
+    let (column, row) = state.affine_cursor;
+    let output_bit = 7 - row as usize;
+    let input_bit = if column < 8 {
+        Some(7 - column as usize)
+    } else {
+        None
+    };
+
+    (output_bit, input_bit)
+}
+
+// Edits to the matrix are turned into `func` and `func2` straight away, so the other modes
+// show the map as it is edited. The arrows and A are used here, so they are taken out of
+// the returned input.
+fn update_affine_editor(state: &mut GameState, mut input: Input) -> Input {
+    let (column, row) = &mut state.affine_cursor;
+    if input.pressed_this_frame(Button::Left) {
+        *column = (*column + 8) % 9;
+    }
+    if input.pressed_this_frame(Button::Right) {
+        *column = (*column + 1) % 9;
+    }
+    if input.pressed_this_frame(Button::Up) {
+        *row = (*row + 7) % 8;
+    }
+    if input.pressed_this_frame(Button::Down) {
+        *row = (*row + 1) % 8;
+    }
+
+    let mut changed = false;
     if input.pressed_this_frame(Button::A) {
-        state.mode = match state.mode {
-            Mode::ViewFunc2 => Mode::VisualizeFunc,
-            Mode::VisualizeFunc => Mode::TestPattern,
-            Mode::TestPattern => Mode::ViewFunc2,
+        match affine_cursor_bits(state) {
+            (output_bit, Some(input_bit)) => state.affine.toggle(output_bit, input_bit),
+            (output_bit, None) => state.affine.toggle_constant(output_bit),
+        }
+        changed = true;
+    }
+    if input.pressed_this_frame(Button::Start) {
+        state.affine = AffineMap::identity();
+        changed = true;
+    }
+
+    if changed {
+        state.func = state.affine.to_func();
+        state.func2 = *state.affine.to_func2();
+    }
+
+    let used = Button::Up | Button::Down | Button::Left | Button::Right | Button::A;
+    input.gamepad.remove(used);
+    input.previous_gamepad.remove(used);
+
+    input
+}
+
+fn render_affine_editor(framebuffer: &mut Framebuffer, state: &GameState) {
+    use rendering::{FONT_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH, SPRITE_SIZE, WHITE_INDEX};
+
+    // Leaves a gap between the matrix and the constant.
+    const CONSTANT_GAP: usize = 4;
+
+    let sprite_size = SPRITE_SIZE as usize;
+    let w = sprite_size * 9 + CONSTANT_GAP + sprite_size * 2;
+    let h = sprite_size * 8 + FONT_SIZE as usize + sprite_size * 2;
+    let x = SCREEN_WIDTH - (w + sprite_size);
+    let y = SCREEN_HEIGHT - (h + sprite_size);
+
+    framebuffer.window(x, y, w, h);
+
+    let affine = &state.affine;
+    for row in 0..8 {
+        for column in 0..9 {
+            let output_bit = 7 - row as usize;
+            let (checked, gap) = if column < 8 {
+                (affine.get(output_bit, 7 - column as usize), 0)
+            } else {
+                (affine.constant & (1 << output_bit) != 0, CONSTANT_GAP)
+            };
+
+            let box_x = x + sprite_size + column as usize * sprite_size + gap;
+            let box_y = y + sprite_size + row as usize * sprite_size;
+            if state.affine_cursor == (column, row) {
+                framebuffer.checkbox_hot(box_x, box_y, checked);
+            } else {
+                framebuffer.checkbox(box_x, box_y, checked);
+            }
+        }
+    }
+
+    let rank = affine.rank();
+    let status = format!(
+        "rank {} {}",
+        rank,
+        if rank == 8 { "invertible" } else { "singular" }
+    );
+    framebuffer.print_line(
+        status.as_bytes(),
+        x + sprite_size,
+        y + sprite_size + sprite_size * 8,
+        WHITE_INDEX,
+    );
+}
+
+fn sbox_name(func: &Func) -> &'static str {
+    library_index(func)
+        .map(|index| LIBRARY[index].0)
+        .unwrap_or("custom")
+}
+
+fn boolean_function_text(func: &Func, bit: u8) -> Vec<u8> {
+    use rendering::NINE_SLICE_MAX_INTERIOR_WIDTH_IN_CHARS;
+
+    let function = BooleanFunction::output_bit(func, bit);
+
+    let weight = function.weight();
+    let mut paragraphs = vec![
+        format!("output bit {}", bit),
+        format!(
+            "weight {} {}",
+            weight,
+            if weight == 128 {
+                "(balanced)".to_string()
+            } else {
+                format!("(off balance by {})", weight as isize - 128)
+            }
+        ),
+        format!(
+            "degree {} nonlinearity {}",
+            function.degree(),
+            function.nonlinearity()
+        ),
+        "anf:".to_string(),
+        function.anf_text(),
+        "truth table, from input 00:".to_string(),
+    ];
+
+    // Four bits to a hex digit, with the lowest input in the most significant bit of
+    // each digit so the table reads left to right.
+    for chunk in function.truth_table.chunks(64) {
+        let digits: String = chunk
+            .chunks(4)
+            .map(|bits| {
+                let nibble = bits.iter().fold(0, |acc, &bit| (acc << 1) | bit as u32);
+                std::char::from_digit(nibble, 16).unwrap()
+            })
+            .collect();
+        paragraphs.push(digits);
+    }
+
+    paragraphs.push("walsh spectrum:".to_string());
+    let spectrum = function.walsh_spectrum();
+    paragraphs.push(
+        spectrum
+            .iter()
+            .enumerate()
+            .map(|(a, w)| format!("{:02x}:{}", a, w))
+            .collect::<Vec<_>>()
+            .join(" "),
+    );
+
+    let width = NINE_SLICE_MAX_INTERIOR_WIDTH_IN_CHARS as usize;
+    let mut text = Vec::new();
+    for paragraph in paragraphs {
+        text.extend(text::bytes_reflow(paragraph.as_bytes(), width));
+        text.push(b'\n');
+    }
+    text
+}
+
+// Left and Right choose the output bit, and Up and Down scroll through the details.
+fn update_and_render_boolean_functions(
+    framebuffer: &mut Framebuffer,
+    state: &mut GameState,
+    input: Input,
+) {
+    use rendering::{
+        NINE_SLICE_MAX_INTERIOR_HEIGHT_IN_CHARS, SCREEN_HEIGHT, SPRITE_SIZE, WHITE_INDEX,
+        YELLOW_INDEX,
+    };
+
+    if input.pressed_this_frame(Button::Start) {
+        state.randomise_func();
+    }
+    if input.pressed_this_frame(Button::Left) {
+        state.boolean_bit = (state.boolean_bit + 7) % 8;
+    }
+    if input.pressed_this_frame(Button::Right) {
+        state.boolean_bit = (state.boolean_bit + 1) % 8;
+    }
+    if input.pressed_this_frame(Button::Up) {
+        state.text_scroll = state.text_scroll.saturating_sub(1);
+    }
+    if input.pressed_this_frame(Button::Down) {
+        state.text_scroll += 1;
+    }
+
+    let text = boolean_function_text(&state.func, state.boolean_bit);
+    let lines: Vec<&[u8]> = text::bytes_lines(&text).collect();
+
+    // Leaves a line free at the bottom for the position.
+    let visible = NINE_SLICE_MAX_INTERIOR_HEIGHT_IN_CHARS as usize - 1;
+    state.text_scroll = state.text_scroll.min(lines.len().saturating_sub(visible));
+
+    framebuffer.clear_to(BLACK);
+
+    let shown = lines[state.text_scroll..]
+        .iter()
+        .take(visible)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(&b'\n');
+    let sprite_size = SPRITE_SIZE as usize;
+    framebuffer.print(&shown, sprite_size, sprite_size, WHITE_INDEX);
+
+    framebuffer.print_line(
+        format!(
+            "line {}/{}",
+            state.text_scroll + 1,
+            lines.len().saturating_sub(visible) + 1
+        )
+        .as_bytes(),
+        sprite_size,
+        SCREEN_HEIGHT - sprite_size,
+        YELLOW_INDEX,
+    );
+}
+
+fn cursor_point(state: &GameState) -> (i8, i8) {
+    i_to_xy(cursor_to_i(state.cursor))
+}
+
+// The arrows move the cursor while held, and holding A, or pressing on the screen with the
+// pointer, paints. B smooths the whole function. The arrows, A and B are taken out of the
+// returned input.
+fn update_func_editor(state: &mut GameState, mut input: Input) -> Input {
+    use rendering::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+    let (dx, dy) = arrow_deltas(input);
+    let (x, y) = state.cursor;
+    state.cursor = (
+        (x as isize + dx).clamp(0, 255) as u8,
+        (y as isize + dy).clamp(0, 255) as u8,
+    );
+
+    // The pointer can also be over the side panel, which isn't part of the grid.
+    let on_grid = |pointer: &Pointer| pointer.x < SCREEN_WIDTH && pointer.y < SCREEN_HEIGHT;
+
+    let mut painting = input.gamepad.contains(Button::A);
+    if let Some(pointer) = input.pointer.filter(on_grid) {
+        let (x, y) = state.viewport.screen_to_source((pointer.x, pointer.y));
+        state.cursor = (x as u8, y as u8);
+        painting |= pointer.pressed;
+    }
+
+    if painting {
+        let point = cursor_point(state);
+        let from = state.last_painted.unwrap_or(point);
+        paint_line(&mut state.func, from, point, state.symmetry);
+        state.last_painted = Some(point);
+    } else {
+        state.last_painted = None;
+    }
+
+    if input.pressed_this_frame(Button::B) {
+        smooth(&mut state.func);
+    }
+    if input.pressed_this_frame(Button::Start) {
+        for (x, y) in state.func.iter_mut().enumerate() {
+            *y = x as u8 as i8;
+        }
+    }
+
+    let used = Button::Up | Button::Down | Button::Left | Button::Right | Button::A | Button::B;
+    input.gamepad.remove(used);
+    input.previous_gamepad.remove(used);
+
+    input
+}
+
+fn render_func_editor(ui: &mut Framebuffer, cursor: &mut Framebuffer, state: &GameState) {
+    use rendering::{WHITE, WHITE_INDEX};
+
+    let viewport = state.viewport;
+    let (x, y) = viewport.source_to_screen((state.cursor.0 as usize, state.cursor.1 as usize));
+    cursor.draw_rect(
+        x.saturating_sub(1),
+        y.saturating_sub(1),
+        viewport.zoom + 2,
+        viewport.zoom + 2,
+        WHITE,
+    );
+
+    let (x, y) = cursor_point(state);
+    let lines = [format!(
+        "({}, {}) f({}) = {} symmetry {}",
+        x,
+        y,
+        x,
+        state.func[x as u8 as usize],
+        state.symmetry.name()
+    )];
+    print_lines_at_bottom(ui, &lines, WHITE_INDEX);
+}
+
+// B starts and stops the sound, Up and Down change the note by a semitone, and Left and
+// Right pick the `Func2` row. Start makes a new waveform.
+fn update_and_render_wavetable(framebuffer: &mut Framebuffer, state: &mut GameState, input: Input) {
+    use rendering::{GREY, SCREEN_HEIGHT, SCREEN_WIDTH, WHITE_INDEX, YELLOW};
+
+    let sonification = &mut state.sonification;
+    if input.pressed_this_frame(Button::B) {
+        sonification.playing = !sonification.playing;
+    }
+    if input.pressed_this_frame(Button::Up) {
+        sonification.change_note(1);
+    }
+    if input.pressed_this_frame(Button::Down) {
+        sonification.change_note(-1);
+    }
+
+    if sonification.source == WavetableSource::Func2Row {
+        if input.pressed_this_frame(Button::Right) {
+            state.x_offset = (state.x_offset as u8).wrapping_add(1) as _;
+        }
+        if input.pressed_this_frame(Button::Left) {
+            state.x_offset = (state.x_offset as u8).wrapping_sub(1) as _;
+        }
+    }
+
+    if input.pressed_this_frame(Button::Start) {
+        let row = match state.sonification.source {
+            WavetableSource::Func => &mut state.func,
+            WavetableSource::Func2Row => &mut state.func2[state.x_offset],
         };
+        state.generators.fill_func(&mut state.rng, row);
+        state.randomised = true;
+    }
+
+    framebuffer.clear_to(BLACK);
+
+    // Drawn in the same order as the plots of `Func`, from -128 to 127, which is the table
+    // played half a cycle out of phase.
+    const MIDDLE: usize = SCREEN_HEIGHT / 2 - 1;
+    framebuffer.draw_filled_rect(0, MIDDLE, SCREEN_WIDTH, 1, GREY);
+
+    let sonification = &state.sonification;
+    let table = sonification.table(&state.func, &state.func2, state.x_offset);
+    let row = |column: usize| (127 - table[(column as u8 ^ 0x80) as usize] as isize) as usize;
+    for column in 0..SCREEN_WIDTH {
+        // Joined up with the previous sample, so steep parts don't leave gaps.
+        let (y, previous) = (row(column), row(column.saturating_sub(1)));
+        let top = y.min(previous);
+        framebuffer.draw_filled_rect(column, top, 1, y.max(previous) - top + 1, BLUE);
+    }
+
+    if sonification.playing {
+        let column = sonification.oscillator.phase as usize as u8 ^ 0x80;
+        framebuffer.draw_filled_rect(column as usize, 0, 1, SCREEN_HEIGHT, YELLOW);
     }
 
-    return match state.mode {
+    let source = match sonification.source {
+        WavetableSource::Func => "func".to_string(),
+        WavetableSource::Func2Row => format!("func2 row {}", state.x_offset),
+    };
+    let lines = [
+        source,
+        format!(
+            "{} {:.1}hz {}",
+            note_name(sonification.note),
+            note_frequency(sonification.note),
+            if sonification.playing {
+                "playing"
+            } else {
+                "stopped"
+            }
+        ),
+    ];
+    print_lines_at_bottom(framebuffer, &lines, WHITE_INDEX);
+}
+
+const HISTORY_FLASH_FRAMES: u8 = 90;
+
+// Every change to the functions goes through here, whichever mode made it, so there is no
+// need for the modes to remember to record them. A stroke being drawn is recorded once it
+// is finished, so it is undone all at once.
+fn update_history(state: &mut GameState) {
+    if state.last_painted.is_some() {
+        return;
+    }
+
+    if state.history.record(&state.func, &state.func2) {
+        state.history_flash = HISTORY_FLASH_FRAMES;
+    }
+}
+
+fn render_history(framebuffer: &mut Framebuffer, state: &mut GameState) {
+    use rendering::{FONT_SIZE, WHITE_INDEX};
+
+    if state.history_flash == 0 {
+        return;
+    }
+    state.history_flash -= 1;
+
+    framebuffer.print_line(
+        format!(
+            "history {}/{}",
+            state.history.position(),
+            state.history.len()
+        )
+        .as_bytes(),
+        2,
+        2 + FONT_SIZE as usize,
+        WHITE_INDEX,
+    );
+}
+
+fn render_export_progress(framebuffer: &mut Framebuffer, state: &GameState) {
+    use rendering::{FONT_SIZE, WHITE_INDEX};
+
+    if let Some((done, count)) = state.export_progress {
+        framebuffer.print_line(
+            format!("exporting {}/{}", done, count).as_bytes(),
+            2,
+            2 + 2 * FONT_SIZE as usize,
+            WHITE_INDEX,
+        );
+    }
+}
+
+const MINIMAP_SIZE: usize = 64;
+
+fn arrow_deltas(input: Input) -> (isize, isize) {
+    let mut dx = 0;
+    let mut dy = 0;
+    if input.gamepad.contains(Button::Left) {
+        dx -= 1;
+    }
+    if input.gamepad.contains(Button::Right) {
+        dx += 1;
+    }
+    if input.gamepad.contains(Button::Up) {
+        dy -= 1;
+    }
+    if input.gamepad.contains(Button::Down) {
+        dy += 1;
+    }
+    (dx, dy)
+}
+
+// Handles the zooming and panning controls, which are shared by every mode. The
+// returned input has the buttons used here removed, so the modes can ignore them.
+fn update_view(state: &mut GameState, mut input: Input) -> Input {
+    let cursor = (state.cursor.0 as usize, state.cursor.1 as usize);
+
+    if input.pressed_this_frame(Button::R) {
+        state.viewport.zoom_in(cursor);
+    }
+    if input.pressed_this_frame(Button::L) {
+        state.viewport.zoom_out(cursor);
+    }
+
+    let arrows = Button::Up | Button::Down | Button::Left | Button::Right;
+    let (dx, dy) = arrow_deltas(input);
+
+    if input.gamepad.contains(Button::X) {
+        let x = (cursor.0 as isize + dx).clamp(0, 255) as usize;
+        let y = (cursor.1 as isize + dy).clamp(0, 255) as usize;
+        state.cursor = (x as u8, y as u8);
+        state.viewport.scroll_to((x, y));
+
+        input.gamepad.remove(arrows);
+        input.previous_gamepad.remove(arrows);
+    } else if input.gamepad.contains(Button::Y) {
+        state.viewport.pan(dx, dy);
+
+        let (v_x, v_y, v_w, v_h) = state.viewport.source_rect();
+        let x = cursor.0.max(v_x).min(v_x + v_w - 1);
+        let y = cursor.1.max(v_y).min(v_y + v_h - 1);
+        state.cursor = (x as u8, y as u8);
+
+        input.gamepad.remove(arrows);
+        input.previous_gamepad.remove(arrows);
+    }
+
+    input
+}
+
+fn render_view(layers: &mut Layers, state: &GameState, input: Input) {
+    use rendering::{MIN_ZOOM, SCREEN_WIDTH, WHITE, WHITE_INDEX};
+
+    let viewport = state.viewport;
+    let zoomed = viewport.zoom > MIN_ZOOM;
+
+    if zoomed {
+        layers.overlay.framebuffer.draw_minimap(
+            &layers.plot,
+            viewport,
+            SCREEN_WIDTH - MINIMAP_SIZE - 2,
+            2,
+            MINIMAP_SIZE,
+        );
+    }
+
+    if zoomed || input.gamepad.contains(Button::X) {
+        let cursor = (state.cursor.0 as usize, state.cursor.1 as usize);
+        let (x, y) = viewport.source_to_screen(cursor);
+        layers.cursor.framebuffer.draw_rect(
+            x.saturating_sub(1),
+            y.saturating_sub(1),
+            viewport.zoom + 2,
+            viewport.zoom + 2,
+            WHITE,
+        );
+
+        let (grid_x, grid_y) = i_to_xy(cursor_to_i(state.cursor));
+        layers.ui.framebuffer.print_line(
+            format!("{}x ({}, {})", viewport.zoom, grid_x, grid_y).as_bytes(),
+            2,
+            2,
+            WHITE_INDEX,
+        );
+    }
+}
+
+fn distinct_count(outputs: &[i8]) -> usize {
+    let mut seen = [false; 256];
+    for &output in outputs {
+        seen[output as u8 as usize] = true;
+    }
+    seen.iter().filter(|&&seen| seen).count()
+}
+
+fn side_panel_lines(state: &GameState) -> Vec<String> {
+    let fixed_points = (0..=255u8)
+        .filter(|&x| state.func[x as usize] as u8 == x)
+        .count();
+    let (b, a) = state.cursor;
+    let row = &state.func2[a as usize];
+
+    let mut lines = vec![
+        "func".to_string(),
+        format!(" {}", sbox_name(&state.func)),
+        format!(" image {}", distinct_count(&state.func)),
+        format!(" fixed points {}", fixed_points),
+        String::new(),
+        format!("func2 row {:02x}", a),
+        format!(" image {}", distinct_count(row)),
+        format!(
+            " {:02x} * {:02x} = {:02x}",
+            a,
+            b,
+            operate(&state.func2, a, b)
+        ),
+        String::new(),
+        format!("offset ({}, {})", state.x_offset, state.y_offset),
+        format!("cursor ({:02x}, {:02x})", b, a),
+        format!("zoom {}x", state.viewport.zoom),
+        format!(
+            "history {}/{}",
+            state.history.position(),
+            state.history.len()
+        ),
+    ];
+
+    if state.mode == Mode::Wavetable {
+        let note = state.sonification.note;
+        lines.push(format!(
+            "note {} {:.1}Hz",
+            note_name(note),
+            note_frequency(note)
+        ));
+    }
+
+    lines
+}
+
+// Framebuffers larger than the grid get statistics in the extra space, on whichever side
+// there is more of it.
+fn render_side_panel(framebuffer: &mut Framebuffer, state: &GameState) {
+    use rendering::{FONT_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH, WHITE_INDEX};
+
+    let extra_width = framebuffer.width.saturating_sub(SCREEN_WIDTH);
+    let extra_height = framebuffer.height.saturating_sub(SCREEN_HEIGHT);
+    if extra_width == 0 && extra_height == 0 {
+        return;
+    }
+
+    for y in 0..framebuffer.height {
+        for x in 0..framebuffer.width {
+            if x >= SCREEN_WIDTH || y >= SCREEN_HEIGHT {
+                let i = framebuffer.xy_to_i(x, y);
+                framebuffer.buffer[i] = BLACK;
+            }
+        }
+    }
+
+    // Lines that are too long are cut off at the edge of the framebuffer.
+    let (x, y, height) = if extra_width >= extra_height {
+        (SCREEN_WIDTH, 0, framebuffer.height)
+    } else {
+        (0, SCREEN_HEIGHT, extra_height)
+    };
+
+    const HEADING_SCALE: u8 = 2;
+
+    framebuffer.print_line_scaled(
+        state.mode.name().as_bytes(),
+        x + 2,
+        y + 2,
+        WHITE_INDEX,
+        HEADING_SCALE,
+    );
+
+    let top = y + 2 + (HEADING_SCALE as usize + 1) * FONT_SIZE as usize;
+    for (i, line) in side_panel_lines(state).iter().enumerate() {
+        let line_y = top + i * FONT_SIZE as usize;
+        if line_y + FONT_SIZE as usize > y + height {
+            break;
+        }
+        framebuffer.print_line(line.as_bytes(), x + 2, line_y, WHITE_INDEX);
+    }
+}
+
+// Drawn after the view so it stays the same size regardless of zoom.
+fn render_scrubber(framebuffer: &mut Framebuffer, state: &GameState, input: Input) {
+    use crate::animation::Axes;
+    use rendering::{FONT_SIZE, GREY, SCREEN_HEIGHT, SCREEN_WIDTH, WHITE_INDEX, YELLOW};
+
+    let animation = &state.animation;
+    if !animation.playing && !input.gamepad.contains(Button::B) {
+        return;
+    }
+
+    const BAR_HEIGHT: usize = 4;
+    let bar_y = SCREEN_HEIGHT - BAR_HEIGHT;
+
+    framebuffer.draw_filled_rect(0, bar_y, SCREEN_WIDTH, BAR_HEIGHT, BLACK);
+
+    let leading_offset = match animation.axes {
+        Axes::Y => state.y_offset,
+        Axes::X | Axes::Both => state.x_offset,
+    };
+    framebuffer.draw_filled_rect(0, bar_y + 1, leading_offset, BAR_HEIGHT - 2, GREY);
+
+    if animation.axes != Axes::Y {
+        framebuffer.draw_filled_rect(state.x_offset, bar_y, 1, BAR_HEIGHT, YELLOW);
+    }
+    if animation.axes != Axes::X {
+        framebuffer.draw_filled_rect(state.y_offset, bar_y, 1, BAR_HEIGHT, GREEN);
+    }
+
+    framebuffer.print_line(
+        animation.status_text().as_bytes(),
+        2,
+        bar_y - FONT_SIZE as usize - 1,
+        WHITE_INDEX,
+    );
+}
+
+// `Map2D` cells have (0, 0) in the bottom left corner, like a graph would.
+fn cell_to_screen_index((x, y): Cell) -> usize {
+    (255 - y as usize) << 8 | x as usize
+}
+
+fn screen_index_to_cell(i: usize) -> Cell {
+    ((i & 0xFF) as u8, 255 - (i >> 8 & 0xFF) as u8)
+}
+
+fn update_and_render_view_map2d(
+    framebuffer: &mut Framebuffer,
+    state: &mut GameState,
+    input: Input,
+) {
+    if input.pressed_this_frame(Button::Right) {
+        state.iterated_map.step_forward();
+    }
+    if input.pressed_this_frame(Button::Left) {
+        state.iterated_map.step_backward();
+    }
+    if input.pressed_this_frame(Button::Start) {
+        state.iterated_map.reset();
+    }
+
+    let iterated_map = &state.iterated_map;
+    state.legend = render_image(
+        framebuffer,
+        state.colouring,
+        &state.colour_map,
+        &state.picture,
+        |i| cell_to_screen_index(iterated_map.apply(screen_index_to_cell(i))),
+    );
+}
+
+// Applies the chosen map to the picture once a frame. Since the maps are bijections the
+// picture always comes back eventually, after the period of the permutation.
+fn update_and_render_scramble_image(
+    framebuffer: &mut Framebuffer,
+    state: &mut GameState,
+    input: Input,
+) {
+    if input.pressed_this_frame(Button::B) {
+        state.is_scrambling = !state.is_scrambling;
+    }
+
+    let (left, right) = (
+        input.pressed_this_frame(Button::Left),
+        input.pressed_this_frame(Button::Right),
+    );
+
+    // Stepping by hand pauses the scrambling, or Left would only cancel out the automatic
+    // step forward.
+    if left || right {
+        state.is_scrambling = false;
+    }
+
+    let iterated_map = &mut state.iterated_map;
+    if input.pressed_this_frame(Button::Start) {
+        iterated_map.reset();
+    }
+
+    if state.is_scrambling {
+        iterated_map.step_forward();
+
+        let period = iterated_map.cycles.as_ref().and_then(|c| c.period);
+        if period == Some(iterated_map.iterations as u128) {
+            iterated_map.reset();
+        }
+    }
+
+    if right {
+        iterated_map.step_forward();
+    }
+    if left {
+        iterated_map.step_backward();
+    }
+
+    for (i, &colour) in state.picture.iter().enumerate() {
+        let target = cell_to_screen_index(iterated_map.apply(screen_index_to_cell(i)));
+        framebuffer.buffer[target] = colour;
+    }
+}
+
+fn print_lines_at_bottom(framebuffer: &mut Framebuffer, lines: &[String], colour: u8) {
+    use rendering::{FONT_SIZE, SCREEN_HEIGHT};
+
+    let mut y = SCREEN_HEIGHT - (lines.len() * FONT_SIZE as usize) - 2;
+    for line in lines {
+        framebuffer.print_line(line.as_bytes(), 2, y, colour);
+        y += FONT_SIZE as usize;
+    }
+}
+
+fn render_scramble_status(framebuffer: &mut Framebuffer, state: &GameState) {
+    use rendering::{WHITE_INDEX, YELLOW_INDEX};
+
+    const MAX_LISTED_LENGTHS: usize = 6;
+
+    let iterated_map = &state.iterated_map;
+
+    let mut lines = Vec::with_capacity(MAX_LISTED_LENGTHS + 3);
+    lines.push(format!(
+        "{} n = {}",
+        iterated_map.kind.name(),
+        iterated_map.iterations
+    ));
+
+    match iterated_map.cycles {
+        Some(ref cycles) => {
+            lines.push(match cycles.period {
+                Some(period) => format!("period {}", period),
+                None => "period too large to show".to_string(),
+            });
+
+            for &(length, count) in cycles.counts.iter().take(MAX_LISTED_LENGTHS) {
+                lines.push(format!("{} cycles of length {}", count, length));
+            }
+            if cycles.counts.len() > MAX_LISTED_LENGTHS {
+                lines.push(format!(
+                    "and {} more lengths",
+                    cycles.counts.len() - MAX_LISTED_LENGTHS
+                ));
+            }
+        }
+        None => lines.push("not a bijection".to_string()),
+    }
+
+    let colour = if iterated_map.iterations == 0 {
+        YELLOW_INDEX
+    } else {
+        WHITE_INDEX
+    };
+
+    print_lines_at_bottom(framebuffer, &lines, colour);
+}
+
+fn render_legend(framebuffer: &mut Framebuffer, state: &GameState) {
+    use rendering::{FONT_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+    const WIDTH: u8 = 64;
+    const HEIGHT: u8 = 6;
+
+    if let Some((ref low, ref high)) = state.legend {
+        // Leaves room below for the scrubber.
+        let x = (SCREEN_WIDTH - WIDTH as usize - 2) as u8;
+        let y = (SCREEN_HEIGHT - (HEIGHT + FONT_SIZE) as usize - 8) as u8;
+        framebuffer.draw_legend(&state.colour_map, (x, y), (WIDTH, HEIGHT), (low, high));
+    }
+}
+
+fn render_map2d_status(framebuffer: &mut Framebuffer, state: &GameState) {
+    use rendering::{FONT_SIZE, SCREEN_HEIGHT, WHITE_INDEX};
+
+    let iterated_map = &state.iterated_map;
+    framebuffer.print_line(
+        format!(
+            "{} n = {}",
+            iterated_map.kind.name(),
+            iterated_map.iterations
+        )
+        .as_bytes(),
+        2,
+        SCREEN_HEIGHT - FONT_SIZE as usize - 2,
+        WHITE_INDEX,
+    );
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Setting {
+    Mode,
+    Colouring,
+    MapKind,
+    MapParameter,
+    SBox,
+    Property,
+    InputPlanes,
+    Symmetry,
+    FuncGenerator,
+    ImageSize,
+    Func2Generator,
+    WavetableSource,
+    Palette,
+    Ramp,
+    Quantise,
+}
+
+// For the modes where Start makes a new function.
+fn push_generator_settings(settings: &mut Vec<Setting>, generators: &Generators, uses_func2: bool) {
+    if uses_func2 {
+        settings.push(Setting::Func2Generator);
+    }
+
+    if !uses_func2 || generators.func2 == Func2Generator::UniqueRows {
+        settings.push(Setting::FuncGenerator);
+
+        if generators.func == FuncGenerator::ImageSize {
+            settings.push(Setting::ImageSize);
+        }
+    }
+}
+
+fn push_colour_map_settings(settings: &mut Vec<Setting>) {
+    settings.push(Setting::Ramp);
+    settings.push(Setting::Quantise);
+}
+
+fn settings(state: &GameState) -> Vec<Setting> {
+    let mut settings = vec![Setting::Mode];
+
+    let generators = &state.generators;
+    match state.mode {
+        Mode::ViewFunc2 => {
+            settings.push(Setting::Colouring);
+            if state.colouring.uses_colour_map() {
+                push_colour_map_settings(&mut settings);
+            }
+            push_generator_settings(&mut settings, generators, true);
+        }
+        Mode::ViewMap2D => {
+            settings.push(Setting::MapKind);
+            settings.push(Setting::MapParameter);
+            settings.push(Setting::Colouring);
+            if state.colouring.uses_colour_map() {
+                push_colour_map_settings(&mut settings);
+            }
+        }
+        Mode::ScrambleImage => {
+            settings.push(Setting::MapKind);
+            settings.push(Setting::MapParameter);
+        }
+        Mode::DifferenceTable | Mode::LinearTable => {
+            settings.push(Setting::SBox);
+            push_colour_map_settings(&mut settings);
+            push_generator_settings(&mut settings, generators, false);
+        }
+        Mode::VisualizeFunc | Mode::BooleanFunctions => {
+            settings.push(Setting::SBox);
+            push_generator_settings(&mut settings, generators, false);
+        }
+        Mode::Structure => {
+            settings.push(Setting::Property);
+            push_colour_map_settings(&mut settings);
+            push_generator_settings(&mut settings, generators, true);
+        }
+        Mode::BitPlanes => {
+            settings.push(Setting::InputPlanes);
+            push_colour_map_settings(&mut settings);
+            push_generator_settings(&mut settings, generators, true);
+        }
+        Mode::DrawFunc => {
+            settings.push(Setting::Symmetry);
+            settings.push(Setting::SBox);
+        }
+        Mode::Wavetable => {
+            settings.push(Setting::WavetableSource);
+            push_generator_settings(&mut settings, generators, false);
+        }
+        Mode::TestPattern | Mode::AffineEditor => {}
+    }
+
+    // Applies to everything, so it goes last to keep the mode specific settings together.
+    settings.push(Setting::Palette);
+
+    settings
+}
+
+fn menu_item(state: &GameState, setting: Setting) -> MenuItem {
+    match setting {
+        Setting::Mode => MenuItem {
+            label: "mode",
+            value: state.mode.name().to_string(),
+        },
+        Setting::Colouring => MenuItem {
+            label: "colouring",
+            value: state.colouring.name().to_string(),
+        },
+        Setting::MapKind => MenuItem {
+            label: "map",
+            value: state.iterated_map.kind.name().to_string(),
+        },
+        Setting::MapParameter => MenuItem {
+            label: "parameter",
+            value: state.iterated_map.parameter.to_string(),
+        },
+        Setting::SBox => MenuItem {
+            label: "s-box",
+            value: sbox_name(&state.func).to_string(),
+        },
+        Setting::Property => MenuItem {
+            label: "property",
+            value: state.property.name().to_string(),
+        },
+        Setting::InputPlanes => MenuItem {
+            label: "inputs",
+            value: state.input_planes.name().to_string(),
+        },
+        Setting::Symmetry => MenuItem {
+            label: "symmetry",
+            value: state.symmetry.name().to_string(),
+        },
+        Setting::FuncGenerator => MenuItem {
+            label: "generator",
+            value: state.generators.func.name().to_string(),
+        },
+        Setting::ImageSize => MenuItem {
+            label: "image size",
+            value: state.generators.image_size.to_string(),
+        },
+        Setting::Func2Generator => MenuItem {
+            label: "func2 generator",
+            value: state.generators.func2.name().to_string(),
+        },
+        Setting::WavetableSource => MenuItem {
+            label: "wavetable",
+            value: state.sonification.source.name().to_string(),
+        },
+        Setting::Palette => MenuItem {
+            label: "palette",
+            value: state.palette_set.name().to_string(),
+        },
+        Setting::Ramp => MenuItem {
+            label: "ramp",
+            value: state.colour_map.ramp.name().to_string(),
+        },
+        Setting::Quantise => MenuItem {
+            label: "quantise",
+            value: if state.colour_map.quantised {
+                "on"
+            } else {
+                "off"
+            }
+            .to_string(),
+        },
+    }
+}
+
+fn change_setting(state: &mut GameState, setting: Setting, delta: i8) {
+    match setting {
+        Setting::Mode => {
+            state.mode = cycle(&Mode::ALL, state.mode, delta);
+        }
+        Setting::Colouring => {
+            state.colouring = cycle(&Colouring::ALL, state.colouring, delta);
+        }
+        Setting::MapKind => {
+            let iterated_map = &mut state.iterated_map;
+            let kind = cycle(&MapKind::ALL, iterated_map.kind, delta);
+            iterated_map.set(kind, iterated_map.parameter);
+        }
+        Setting::MapParameter => {
+            let iterated_map = &mut state.iterated_map;
+            let parameter = iterated_map.parameter.wrapping_add(delta as u8);
+            iterated_map.set(iterated_map.kind, parameter);
+        }
+        Setting::SBox => {
+            let len = LIBRARY.len() as isize;
+            let index = match library_index(&state.func) {
+                Some(index) => (index as isize + delta as isize).rem_euclid(len),
+                // Coming from a custom `Func`, so start from whichever end is that way.
+                None if delta > 0 => 0,
+                None => len - 1,
+            };
+            state.func = LIBRARY[index as usize].1;
+        }
+        Setting::Property => {
+            state.property = cycle(&Property::ALL, state.property, delta);
+        }
+        Setting::InputPlanes => {
+            state.input_planes = cycle(&InputPlanes::ALL, state.input_planes, delta);
+        }
+        Setting::Symmetry => {
+            state.symmetry = cycle(&Symmetry::ALL, state.symmetry, delta);
+        }
+        Setting::FuncGenerator => {
+            let generators = &mut state.generators;
+            generators.func = cycle(&FuncGenerator::ALL, generators.func, delta);
+        }
+        Setting::ImageSize => {
+            // Wraps around, counting from 1 up to 256.
+            let generators = &mut state.generators;
+            let size =
+                (generators.image_size as i16 - 1 + delta as i16).rem_euclid(MAX_IMAGE_SIZE as i16);
+            generators.image_size = size as u16 + 1;
+        }
+        Setting::Func2Generator => {
+            let generators = &mut state.generators;
+            generators.func2 = cycle(&Func2Generator::ALL, generators.func2, delta);
+        }
+        Setting::WavetableSource => {
+            let sonification = &mut state.sonification;
+            sonification.source = cycle(&WavetableSource::ALL, sonification.source, delta);
+        }
+        Setting::Palette => {
+            state.palette_set = cycle(&PaletteSet::ALL, state.palette_set, delta);
+        }
+        Setting::Ramp => {
+            let colour_map = &mut state.colour_map;
+            colour_map.ramp = cycle(&Ramp::ALL, colour_map.ramp, delta);
+        }
+        Setting::Quantise => {
+            state.colour_map.quantised = !state.colour_map.quantised;
+        }
+    }
+}
+
+fn update_menu(state: &mut GameState, input: Input) -> Input {
+    let settings = settings(state);
+    let (change, input) = state.menu.update(settings.len(), input);
+
+    if let Some((index, delta)) = change {
+        change_setting(state, settings[index], delta);
+    }
+
+    input
+}
+
+fn render_menu(framebuffer: &mut Framebuffer, state: &GameState) {
+    let items: Vec<MenuItem> = settings(state)
+        .into_iter()
+        .map(|setting| menu_item(state, setting))
+        .collect();
+
+    state.menu.render(framebuffer, &items);
+}
+
+// Whether the plot could look any different from last frame. What the modes draw only
+// changes with the buttons or the pointer, while something is playing, or from outside of
+// a frame, which sets `redraw_plot`. The rest of the time the plot is left as it is, which
+// also leaves the legend that went with it.
+fn plot_needs_redraw(state: &GameState, input: Input) -> bool {
+    let pressing = matches!(input.pointer, Some(Pointer { pressed: true, .. }));
+    let playing = state.animation.playing
+        || state.sonification.playing
+        || (state.mode == Mode::ScrambleImage && state.is_scrambling);
+
+    state.redraw_plot
+        || !input.gamepad.is_empty()
+        || input.gamepad != input.previous_gamepad
+        || pressing
+        || playing
+}
+
+// Everything below the overlays, which is all that `export_sweep` needs.
+fn update_and_render_plot(plot: &mut Framebuffer, state: &mut GameState, input: Input) {
+    match state.mode {
         Mode::ViewFunc2 => {
-            update_and_render_view_func2(framebuffer, state, input);
+            update_and_render_view_func2(plot, state, input);
         }
         Mode::VisualizeFunc => {
-            update_and_render_visualize_func(framebuffer, state, input);
+            update_and_render_visualize_func(plot, state, input);
         }
         Mode::TestPattern => {
-            update_and_render_test_pattern(framebuffer, state, input);
+            update_and_render_test_pattern(plot, state, input);
+        }
+        Mode::ViewMap2D => {
+            update_and_render_view_map2d(plot, state, input);
+        }
+        Mode::ScrambleImage => {
+            update_and_render_scramble_image(plot, state, input);
         }
+        Mode::DifferenceTable | Mode::LinearTable => {
+            update_and_render_sbox_table(plot, state, input);
+        }
+        Mode::Structure => {
+            update_and_render_structure(plot, state, input);
+        }
+        Mode::AffineEditor | Mode::DrawFunc => {
+            apply_func(plot, state);
+        }
+        Mode::BitPlanes => {
+            if input.pressed_this_frame(Button::Start) {
+                state.randomise_func2();
+            }
+            render_bit_planes(plot, &state.func2, state.input_planes, &state.colour_map);
+            state.legend = Some(("0".to_string(), "1".to_string()));
+        }
+        Mode::BooleanFunctions => {
+            update_and_render_boolean_functions(plot, state, input);
+        }
+        Mode::Wavetable => {
+            update_and_render_wavetable(plot, state, input);
+        }
+    }
+}
+
+#[inline]
+pub fn update_and_render(
+    framebuffer: &mut Framebuffer,
+    layers: &mut Layers,
+    state: &mut GameState,
+    input: Input,
+    speaker: &mut Speaker,
+) {
+    let mode = state.mode;
+    let redraw_plot = plot_needs_redraw(state, input);
+
+    let input = update_menu(state, input);
+    let input = update_view(state, input);
+    let input = match state.mode {
+        Mode::AffineEditor => update_affine_editor(state, input),
+        Mode::DrawFunc => update_func_editor(state, input),
+        _ => input,
     };
+
+    if input.pressed_this_frame(Button::A) {
+        state.mode = cycle(&Mode::ALL, state.mode, 1);
+    }
+
+    layers.clear_above_plot();
+    state.randomised = false;
+
+    if redraw_plot {
+        state.redraw_plot = false;
+        state.legend = None;
+        update_and_render_plot(&mut layers.plot, state, input);
+    }
+
+    if state.randomised {
+        speaker.request_sfx(RANDOMISE);
+    }
+
+    render_view(layers, state, input);
+
+    let (ui, cursor) = (&mut layers.ui.framebuffer, &mut layers.cursor.framebuffer);
+
+    match state.mode {
+        Mode::ViewFunc2 => render_scrubber(ui, state, input),
+        Mode::ViewMap2D => render_map2d_status(ui, state),
+        Mode::ScrambleImage => render_scramble_status(ui, state),
+        Mode::DifferenceTable | Mode::LinearTable => render_sbox_status(ui, state),
+        Mode::Structure => render_structure_status(ui, state),
+        Mode::AffineEditor => render_affine_editor(ui, state),
+        Mode::DrawFunc => render_func_editor(ui, cursor, state),
+        Mode::VisualizeFunc
+        | Mode::TestPattern
+        | Mode::BitPlanes
+        | Mode::BooleanFunctions
+        | Mode::Wavetable => {}
+    }
+
+    render_legend(ui, state);
+
+    update_history(state);
+    render_history(ui, state);
+
+    render_export_progress(ui, state);
+
+    render_menu(ui, state);
+
+    layers.composite(state.viewport, framebuffer);
+    render_side_panel(framebuffer, state);
+
+    framebuffer.screen_palette = state.palette_set.colours();
+    framebuffer.apply_screen_palette();
+
+    if state.mode != mode {
+        speaker.request_sfx(MODE_CHANGE);
+    }
 }
@@ -0,0 +1,295 @@
+use crate::affine::AffineMap;
+use crate::game::{Func, Func2};
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use std::collections::HashSet;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FuncGenerator {
+    Uniform,
+    Permutation,
+    // A permutation that is its own inverse.
+    Involution,
+    // Exactly `image_size` different outputs.
+    ImageSize,
+    Monotone,
+    RandomWalk,
+    SmoothNoise,
+    Affine,
+}
+
+impl FuncGenerator {
+    pub const ALL: [FuncGenerator; 8] = [
+        FuncGenerator::Uniform,
+        FuncGenerator::Permutation,
+        FuncGenerator::Involution,
+        FuncGenerator::ImageSize,
+        FuncGenerator::Monotone,
+        FuncGenerator::RandomWalk,
+        FuncGenerator::SmoothNoise,
+        FuncGenerator::Affine,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FuncGenerator::Uniform => "uniform",
+            FuncGenerator::Permutation => "permutation",
+            FuncGenerator::Involution => "involution",
+            FuncGenerator::ImageSize => "image size",
+            FuncGenerator::Monotone => "monotone",
+            FuncGenerator::RandomWalk => "random walk",
+            FuncGenerator::SmoothNoise => "smooth noise",
+            FuncGenerator::Affine => "affine",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Func2Generator {
+    Uniform,
+    // Each row comes from the `Func` generator, and no two rows are the same, so no frame
+    // shows up twice as the offset changes.
+    UniqueRows,
+}
+
+impl Func2Generator {
+    pub const ALL: [Func2Generator; 2] = [Func2Generator::Uniform, Func2Generator::UniqueRows];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Func2Generator::Uniform => "uniform",
+            Func2Generator::UniqueRows => "unique rows",
+        }
+    }
+}
+
+pub const MIN_IMAGE_SIZE: u16 = 1;
+pub const MAX_IMAGE_SIZE: u16 = 256;
+
+// Some generators can only make so many different rows, like the 256 constant functions,
+// so this gives up on avoiding a duplicate eventually rather than looping forever.
+const MAX_ATTEMPTS_PER_ROW: usize = 10_000;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Generators {
+    pub func: FuncGenerator,
+    pub func2: Func2Generator,
+    pub image_size: u16,
+}
+
+impl Default for Generators {
+    fn default() -> Self {
+        Generators {
+            func: FuncGenerator::Uniform,
+            func2: Func2Generator::Uniform,
+            image_size: 16,
+        }
+    }
+}
+
+impl Generators {
+    pub fn fill_func<R: Rng>(&self, rng: &mut R, func: &mut Func) {
+        match self.func {
+            FuncGenerator::Uniform => rng.fill(func),
+            FuncGenerator::Permutation => {
+                fill_identity(func);
+                func.shuffle(rng);
+            }
+            FuncGenerator::Involution => fill_involution(rng, func),
+            FuncGenerator::ImageSize => fill_with_image_size(rng, func, self.image_size),
+            FuncGenerator::Monotone => {
+                rng.fill(func);
+                func.sort();
+                from_ascending_order(func);
+            }
+            FuncGenerator::RandomWalk => fill_random_walk(rng, func),
+            FuncGenerator::SmoothNoise => fill_smooth_noise(rng, func),
+            FuncGenerator::Affine => {
+                let mut affine = AffineMap::identity();
+                rng.fill(&mut affine.rows);
+                affine.constant = rng.gen();
+                *func = affine.to_func();
+            }
+        }
+    }
+
+    pub fn fill_func2<R: Rng>(&self, rng: &mut R, func2: &mut Func2) {
+        match self.func2 {
+            Func2Generator::Uniform => {
+                for row in func2.iter_mut() {
+                    rng.fill(row);
+                }
+            }
+            Func2Generator::UniqueRows => {
+                let mut seen = HashSet::with_capacity(256);
+                for row in func2.iter_mut() {
+                    for _ in 0..MAX_ATTEMPTS_PER_ROW {
+                        self.fill_func(rng, row);
+                        if seen.insert(row.to_vec()) {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn fill_identity(func: &mut Func) {
+    for (x, y) in func.iter_mut().enumerate() {
+        *y = x as u8 as i8;
+    }
+}
+
+// The generators that think in terms of the plot produce values for x from -128 up to
+// 127, which is not the order `Func` is indexed in.
+fn from_ascending_order(func: &mut Func) {
+    let ascending = *func;
+    for (i, &y) in ascending.iter().enumerate() {
+        let x = (i as i16 - 128) as i8;
+        func[x as u8 as usize] = y;
+    }
+}
+
+// Pairs up the inputs at random, then swaps about half of the pairs, leaving the rest of
+// the inputs as fixed points.
+fn fill_involution<R: Rng>(rng: &mut R, func: &mut Func) {
+    fill_identity(func);
+
+    let mut inputs: Vec<usize> = (0..256).collect();
+    inputs.shuffle(rng);
+
+    for pair in inputs.chunks(2) {
+        if rng.gen() {
+            func[pair[0]] = pair[1] as u8 as i8;
+            func[pair[1]] = pair[0] as u8 as i8;
+        }
+    }
+}
+
+fn fill_with_image_size<R: Rng>(rng: &mut R, func: &mut Func, image_size: u16) {
+    let image_size = image_size.clamp(MIN_IMAGE_SIZE, MAX_IMAGE_SIZE) as usize;
+
+    let mut outputs: Vec<i8> = (0..256).map(|y| y as u8 as i8).collect();
+    outputs.shuffle(rng);
+    outputs.truncate(image_size);
+
+    let mut inputs: Vec<usize> = (0..256).collect();
+    inputs.shuffle(rng);
+
+    // Every chosen output gets at least one input, so the image is exactly that size.
+    for (i, &x) in inputs.iter().enumerate() {
+        func[x] = if i < image_size {
+            outputs[i]
+        } else {
+            *outputs.choose(rng).unwrap()
+        };
+    }
+}
+
+fn fill_random_walk<R: Rng>(rng: &mut R, func: &mut Func) {
+    const MAX_STEP: i16 = 4;
+
+    let mut y: i16 = rng.gen::<i8>() as i16;
+    for value in func.iter_mut() {
+        *value = y as i8;
+        y = (y + rng.gen_range(-MAX_STEP, MAX_STEP + 1)).clamp(-128, 127);
+    }
+
+    from_ascending_order(func);
+}
+
+// Random heights at evenly spaced points, with smooth curves between them.
+fn fill_smooth_noise<R: Rng>(rng: &mut R, func: &mut Func) {
+    const SPACING: usize = 32;
+
+    let heights: Vec<f32> = (0..=256 / SPACING)
+        .map(|_| rng.gen::<i8>() as f32)
+        .collect();
+
+    for (i, value) in func.iter_mut().enumerate() {
+        let (segment, offset) = (i / SPACING, (i % SPACING) as f32 / SPACING as f32);
+        let t = offset * offset * (3.0 - 2.0 * offset);
+        let height = heights[segment] + (heights[segment + 1] - heights[segment]) * t;
+        *value = height.round() as i8;
+    }
+
+    from_ascending_order(func);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    fn generate(generator: FuncGenerator, image_size: u16) -> Func {
+        let mut rng = XorShiftRng::from_seed([7; 16]);
+        let generators = Generators {
+            func: generator,
+            image_size,
+            ..Generators::default()
+        };
+
+        let mut func = [0; 256];
+        generators.fill_func(&mut rng, &mut func);
+        func
+    }
+
+    fn image_size(func: &Func) -> usize {
+        func.iter().collect::<HashSet<_>>().len()
+    }
+
+    fn at(func: &Func, x: i16) -> i8 {
+        func[x as i8 as u8 as usize]
+    }
+
+    #[test]
+    fn permutations_and_involutions_are_bijections() {
+        let permutation = generate(FuncGenerator::Permutation, 0);
+        assert_eq!(image_size(&permutation), 256);
+
+        let involution = generate(FuncGenerator::Involution, 0);
+        for x in 0..256 {
+            let y = involution[x] as u8 as usize;
+            assert_eq!(involution[y] as u8 as usize, x);
+        }
+    }
+
+    #[test]
+    fn image_size_is_exact() {
+        for &size in &[1, 2, 100, 256] {
+            assert_eq!(
+                image_size(&generate(FuncGenerator::ImageSize, size)),
+                size as usize
+            );
+        }
+    }
+
+    #[test]
+    fn monotone_functions_never_go_down() {
+        let func = generate(FuncGenerator::Monotone, 0);
+
+        for x in -128..127 {
+            assert!(at(&func, x) <= at(&func, x + 1));
+        }
+    }
+
+    #[test]
+    fn unique_rows_are_unique_even_when_there_are_only_just_enough() {
+        let mut rng = XorShiftRng::from_seed([7; 16]);
+        let generators = Generators {
+            func: FuncGenerator::ImageSize,
+            func2: Func2Generator::UniqueRows,
+            image_size: 1,
+        };
+
+        let mut func2 = Box::new([[0; 256]; 256]);
+        generators.fill_func2(&mut rng, &mut func2);
+
+        let rows: HashSet<Vec<i8>> = func2.iter().map(|row| row.to_vec()).collect();
+        assert_eq!(rows.len(), 256);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::game::{Func, Func2};
+
+use std::collections::VecDeque;
+
+pub const HISTORY_CAPACITY: usize = 64;
+
+// The XOR of the cells before and after a change, so the same delta both undoes and redoes
+// it. Edits that only touch a few cells just keep those, which matters for the 64KB `Func2`.
+#[derive(Clone, Debug)]
+enum Delta {
+    Sparse(Vec<(u16, i8)>),
+    Dense(Vec<i8>),
+}
+
+// `Func` and `Func2` are both handled as a slice of rows, `Func` having just the one.
+fn cells<'a>(rows: &'a [[i8; 256]]) -> impl Iterator<Item = i8> + 'a {
+    rows.iter().flat_map(|row| row.iter().cloned())
+}
+
+impl Delta {
+    fn between(before: &[[i8; 256]], after: &[[i8; 256]]) -> Option<Delta> {
+        let changed: Vec<(u16, i8)> = cells(before)
+            .zip(cells(after))
+            .enumerate()
+            .filter(|&(_, (b, a))| b != a)
+            .map(|(i, (b, a))| (i as u16, b ^ a))
+            .collect();
+
+        if changed.is_empty() {
+            return None;
+        }
+
+        // Each sparse entry takes three bytes to the dense one's one.
+        let cell_count = before.len() * 256;
+        Some(if changed.len() * 3 < cell_count {
+            Delta::Sparse(changed)
+        } else {
+            Delta::Dense(
+                cells(before)
+                    .zip(cells(after))
+                    .map(|(b, a)| b ^ a)
+                    .collect(),
+            )
+        })
+    }
+
+    fn apply(&self, rows: &mut [[i8; 256]]) {
+        match self {
+            Delta::Sparse(changed) => {
+                for &(i, xor) in changed {
+                    rows[i as usize / 256][i as usize % 256] ^= xor;
+                }
+            }
+            Delta::Dense(xors) => {
+                let cells = rows.iter_mut().flat_map(|row| row.iter_mut());
+                for (cell, xor) in cells.zip(xors.iter()) {
+                    *cell ^= xor;
+                }
+            }
+        }
+    }
+}
+
+// Some edits change both functions at once, and those are undone together.
+#[derive(Clone, Debug)]
+struct Change {
+    func: Option<Delta>,
+    func2: Option<Delta>,
+}
+
+#[derive(Clone)]
+pub struct History {
+    changes: VecDeque<Change>,
+    // How many of `changes` are currently applied. The ones after that can be redone.
+    position: usize,
+    // What the functions were when last recorded, to find out what has changed since.
+    func: Func,
+    func2: Box<Func2>,
+}
+
+impl History {
+    pub fn new(func: &Func, func2: &Func2) -> Self {
+        History {
+            changes: VecDeque::with_capacity(HISTORY_CAPACITY),
+            position: 0,
+            func: *func,
+            func2: Box::new(*func2),
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    // Records whatever has changed since the last call as a single step, dropping anything
+    // that could have been redone. Returns whether there was anything to record.
+    pub fn record(&mut self, func: &Func, func2: &Func2) -> bool {
+        let change = Change {
+            func: Delta::between(std::slice::from_ref(&self.func), std::slice::from_ref(func)),
+            func2: Delta::between(&self.func2[..], &func2[..]),
+        };
+
+        if change.func.is_none() && change.func2.is_none() {
+            return false;
+        }
+
+        self.changes.truncate(self.position);
+        if self.changes.len() >= HISTORY_CAPACITY {
+            self.changes.pop_front();
+        }
+        self.changes.push_back(change);
+        self.position = self.changes.len();
+
+        self.func = *func;
+        self.func2.copy_from_slice(&func2[..]);
+
+        true
+    }
+
+    pub fn undo(&mut self, func: &mut Func, func2: &mut Func2) -> bool {
+        if self.position == 0 {
+            return false;
+        }
+        self.position -= 1;
+
+        self.apply(self.position, func, func2);
+
+        true
+    }
+
+    pub fn redo(&mut self, func: &mut Func, func2: &mut Func2) -> bool {
+        if self.position >= self.changes.len() {
+            return false;
+        }
+
+        self.apply(self.position, func, func2);
+
+        self.position += 1;
+
+        true
+    }
+
+    fn apply(&mut self, index: usize, func: &mut Func, func2: &mut Func2) {
+        let change = &self.changes[index];
+
+        if let Some(ref delta) = change.func {
+            delta.apply(std::slice::from_mut(func));
+            delta.apply(std::slice::from_mut(&mut self.func));
+        }
+        if let Some(ref delta) = change.func2 {
+            delta.apply(&mut func2[..]);
+            delta.apply(&mut self.func2[..]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo_step_through_the_recorded_states() {
+        let mut func = [0; 256];
+        let mut func2 = Box::new([[0; 256]; 256]);
+        let mut history = History::new(&func, &func2);
+
+        func[3] = 7;
+        assert!(history.record(&func, &func2));
+
+        for row in func2.iter_mut() {
+            for (b, cell) in row.iter_mut().enumerate() {
+                *cell = b as i8;
+            }
+        }
+        func[4] = -1;
+        assert!(history.record(&func, &func2));
+        assert!(!history.record(&func, &func2));
+
+        assert!(history.undo(&mut func, &mut func2));
+        assert_eq!((func[3], func[4], func2[9][9]), (7, 0, 0));
+
+        assert!(history.undo(&mut func, &mut func2));
+        assert_eq!(func[3], 0);
+        assert!(!history.undo(&mut func, &mut func2));
+
+        assert!(history.redo(&mut func, &mut func2));
+        assert!(history.redo(&mut func, &mut func2));
+        assert_eq!((func[3], func[4], func2[9][9]), (7, -1, 9));
+        assert!(!history.redo(&mut func, &mut func2));
+    }
+
+    #[test]
+    fn recording_after_undoing_drops_the_redos() {
+        let mut func = [0; 256];
+        let mut func2 = Box::new([[0; 256]; 256]);
+        let mut history = History::new(&func, &func2);
+
+        for i in 1..=3 {
+            func[0] = i;
+            history.record(&func, &func2);
+        }
+        history.undo(&mut func, &mut func2);
+        history.undo(&mut func, &mut func2);
+
+        func2[1][2] = 5;
+        history.record(&func, &func2);
+
+        assert_eq!((history.position(), history.len()), (2, 2));
+        assert!(!history.redo(&mut func, &mut func2));
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut func = [0; 256];
+        let func2 = Box::new([[0; 256]; 256]);
+        let mut history = History::new(&func, &func2);
+
+        for i in 0..HISTORY_CAPACITY + 10 {
+            func[i % 256] = 1;
+            func[(i + 1) % 256] = 2;
+            history.record(&func, &func2);
+        }
+
+        assert_eq!(history.len(), HISTORY_CAPACITY);
+    }
+}
@@ -1,3 +1,41 @@
+mod affine;
+pub use self::affine::*;
+
+mod animation;
+pub use self::animation::*;
+
+mod bit_planes;
+pub use self::bit_planes::*;
+
+mod boolean;
+pub use self::boolean::*;
+
+mod map2d;
+pub use self::map2d::*;
+
+mod menu;
+pub use self::menu::*;
+
+mod sbox;
+pub use self::sbox::*;
+
+mod structure;
+pub use self::structure::*;
+
+mod func_editor;
+pub use self::func_editor::*;
+
+mod generators;
+pub use self::generators::*;
+
+mod history;
+pub use self::history::*;
+
+mod sonification;
+pub use self::sonification::*;
+
+mod sounds;
+pub use self::sounds::*;
 
 mod game;
 pub use self::game::*;
@@ -0,0 +1,439 @@
+// A map from the 256 by 256 grid to itself. Unlike a `Func2`, which transforms x and y
+// separately, these can mix the two coordinates together.
+//
+// Cells are `(x, y)` pairs of `u8`s, which makes the usual "mod 256" maps fall out of
+// wrapping arithmetic.
+pub type Cell = (u8, u8);
+
+pub const CELL_COUNT: usize = 256 * 256;
+
+#[inline]
+pub fn cell_to_index((x, y): Cell) -> usize {
+    (y as usize) << 8 | x as usize
+}
+
+#[inline]
+pub fn index_to_cell(i: usize) -> Cell {
+    ((i & 0xFF) as u8, (i >> 8 & 0xFF) as u8)
+}
+
+pub enum Map2D {
+    // Indexed by `cell_to_index`, and holding the `cell_to_index` of the output.
+    Table(Vec<u16>),
+    Closure(Box<dyn Fn(Cell) -> Cell>),
+}
+
+// Closures can't be cloned, so the copy is always a table.
+impl Clone for Map2D {
+    fn clone(&self) -> Self {
+        self.to_table()
+    }
+}
+
+impl Map2D {
+    pub fn from_fn<F: Fn(Cell) -> Cell + 'static>(f: F) -> Self {
+        Map2D::Closure(Box::new(f))
+    }
+
+    pub fn identity() -> Self {
+        Map2D::Table((0..CELL_COUNT).map(|i| i as u16).collect())
+    }
+
+    #[inline]
+    pub fn apply(&self, cell: Cell) -> Cell {
+        match self {
+            Map2D::Table(table) => index_to_cell(table[cell_to_index(cell)] as usize),
+            Map2D::Closure(f) => f(cell),
+        }
+    }
+
+    pub fn to_table(&self) -> Map2D {
+        Map2D::Table(self.table_entries())
+    }
+
+    fn table_entries(&self) -> Vec<u16> {
+        match self {
+            Map2D::Table(table) => table.clone(),
+            Map2D::Closure(f) => (0..CELL_COUNT)
+                .map(|i| cell_to_index(f(index_to_cell(i))) as u16)
+                .collect(),
+        }
+    }
+
+    // Applies `self` and then `other`.
+    pub fn then(&self, other: &Map2D) -> Map2D {
+        Map2D::Table(
+            (0..CELL_COUNT)
+                .map(|i| cell_to_index(other.apply(self.apply(index_to_cell(i)))) as u16)
+                .collect(),
+        )
+    }
+
+    // `self` applied `n` times.
+    pub fn power(&self, mut n: usize) -> Map2D {
+        let mut result = Map2D::identity();
+        let mut square = self.to_table();
+
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result.then(&square);
+            }
+            n >>= 1;
+            if n > 0 {
+                square = square.then(&square);
+            }
+        }
+
+        result
+    }
+
+    pub fn is_bijective(&self) -> bool {
+        let mut seen = vec![false; CELL_COUNT];
+        for i in 0..CELL_COUNT {
+            let target = cell_to_index(self.apply(index_to_cell(i)));
+            if seen[target] {
+                return false;
+            }
+            seen[target] = true;
+        }
+
+        true
+    }
+
+    // Only bijections are made up of cycles, so this returns `None` for anything else.
+    pub fn cycle_structure(&self) -> Option<CycleStructure> {
+        if !self.is_bijective() {
+            return None;
+        }
+
+        let mut visited = vec![false; CELL_COUNT];
+        let mut counts = std::collections::BTreeMap::new();
+
+        for start in 0..CELL_COUNT {
+            if visited[start] {
+                continue;
+            }
+
+            let mut length = 0;
+            let mut i = start;
+            while !visited[i] {
+                visited[i] = true;
+                length += 1;
+                i = cell_to_index(self.apply(index_to_cell(i)));
+            }
+
+            *counts.entry(length).or_insert(0) += 1;
+        }
+
+        let mut period = Some(1u128);
+        for &length in counts.keys() {
+            period = period.and_then(|p| lcm(p, length as u128));
+        }
+
+        Some(CycleStructure {
+            counts: counts.into_iter().collect(),
+            period,
+        })
+    }
+
+    pub fn inverse(&self) -> Option<Map2D> {
+        if !self.is_bijective() {
+            return None;
+        }
+
+        let mut table = vec![0; CELL_COUNT];
+        for i in 0..CELL_COUNT {
+            table[cell_to_index(self.apply(index_to_cell(i)))] = i as u16;
+        }
+
+        Some(Map2D::Table(table))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CycleStructure {
+    // Pairs of cycle length and how many cycles of that length there are, shortest first.
+    pub counts: Vec<(usize, usize)>,
+    // The number of iterations until every cell is back where it started. `None` means
+    // the period did not fit in a `u128`, which can happen for arbitrary permutations.
+    pub period: Option<u128>,
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        let temp = b;
+        b = a % b;
+        a = temp;
+    }
+    a
+}
+
+fn lcm(a: u128, b: u128) -> Option<u128> {
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+// A map along with the result of iterating it some number of times. The inverse is kept
+// around so stepping in either direction costs a single composition.
+#[derive(Clone)]
+pub struct IteratedMap {
+    pub kind: MapKind,
+    pub parameter: u8,
+    pub iterations: usize,
+    pub cycles: Option<CycleStructure>,
+    map: Map2D,
+    inverse: Option<Map2D>,
+    power: Map2D,
+}
+
+impl IteratedMap {
+    pub fn new(kind: MapKind, parameter: u8) -> Self {
+        let map = kind.map(parameter).to_table();
+        let inverse = map.inverse();
+        let cycles = map.cycle_structure();
+
+        IteratedMap {
+            kind,
+            parameter,
+            iterations: 0,
+            cycles,
+            map,
+            inverse,
+            power: Map2D::identity(),
+        }
+    }
+
+    // Switches to a different map, starting again from zero iterations.
+    pub fn set(&mut self, kind: MapKind, parameter: u8) {
+        *self = IteratedMap::new(kind, parameter);
+    }
+
+    pub fn step_forward(&mut self) {
+        self.power = self.power.then(&self.map);
+        self.iterations += 1;
+    }
+
+    pub fn step_backward(&mut self) {
+        if self.iterations == 0 {
+            return;
+        }
+        self.iterations -= 1;
+
+        self.power = match self.inverse {
+            Some(ref inverse) => self.power.then(inverse),
+            None => self.map.power(self.iterations),
+        };
+    }
+
+    pub fn reset(&mut self) {
+        self.iterations = 0;
+        self.power = Map2D::identity();
+    }
+
+    #[inline]
+    pub fn apply(&self, cell: Cell) -> Cell {
+        self.power.apply(cell)
+    }
+}
+
+impl Default for IteratedMap {
+    fn default() -> Self {
+        IteratedMap::new(MapKind::default(), 1)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MapKind {
+    #[default]
+    CatMap,
+    Baker,
+    HorizontalShear,
+    VerticalShear,
+    Rotation,
+    XorMix,
+}
+
+impl MapKind {
+    pub const ALL: [MapKind; 6] = [
+        MapKind::CatMap,
+        MapKind::Baker,
+        MapKind::HorizontalShear,
+        MapKind::VerticalShear,
+        MapKind::Rotation,
+        MapKind::XorMix,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            MapKind::CatMap => "cat map",
+            MapKind::Baker => "baker's map",
+            MapKind::HorizontalShear => "horizontal shear",
+            MapKind::VerticalShear => "vertical shear",
+            MapKind::Rotation => "rotation",
+            MapKind::XorMix => "xor mix",
+        }
+    }
+
+    // The meaning of `parameter` depends on the kind. It is the amount for the shears,
+    // the angle in 256ths of a turn for the rotation, and ignored by the rest.
+    pub fn map(&self, parameter: u8) -> Map2D {
+        match *self {
+            MapKind::CatMap => cat_map(),
+            MapKind::Baker => baker_map(),
+            MapKind::HorizontalShear => horizontal_shear(parameter),
+            MapKind::VerticalShear => vertical_shear(parameter),
+            MapKind::Rotation => rotation(parameter),
+            MapKind::XorMix => xor_mix(),
+        }
+    }
+}
+
+// Arnold's cat map: (x, y) -> (2x + y, x + y) mod 256.
+pub fn cat_map() -> Map2D {
+    Map2D::from_fn(|(x, y)| (x.wrapping_mul(2).wrapping_add(y), x.wrapping_add(y)))
+}
+
+// The discrete baker's map: the grid is stretched to twice the width and half the height,
+// then the right half is cut off and stacked on top of the left half.
+pub fn baker_map() -> Map2D {
+    Map2D::from_fn(|(x, y)| {
+        let low_bit = y & 1;
+        if x < 128 {
+            (2 * x + low_bit, y >> 1)
+        } else {
+            (2 * (x - 128) + low_bit, (y >> 1) + 128)
+        }
+    })
+}
+
+pub fn horizontal_shear(amount: u8) -> Map2D {
+    Map2D::from_fn(move |(x, y)| (x.wrapping_add(y.wrapping_mul(amount)), y))
+}
+
+pub fn vertical_shear(amount: u8) -> Map2D {
+    Map2D::from_fn(move |(x, y)| (x, y.wrapping_add(x.wrapping_mul(amount))))
+}
+
+// A rotation about the centre of the grid, built out of three shears so that it stays
+// a bijection even though the angle is not a multiple of a quarter turn.
+pub fn rotation(angle: u8) -> Map2D {
+    let quarter_turns = angle / 64;
+    let remainder = (angle % 64) as f32 / 256.0 * 2.0 * std::f32::consts::PI;
+
+    let alpha = -(remainder / 2.0).tan();
+    let beta = remainder.sin();
+
+    Map2D::from_fn(move |(x, y)| {
+        let (mut x, mut y) = (x.wrapping_sub(128) as i8, y.wrapping_sub(128) as i8);
+
+        for _ in 0..quarter_turns {
+            let temp = x;
+            x = y.wrapping_neg().wrapping_sub(1);
+            y = temp;
+        }
+
+        x = x.wrapping_add((alpha * y as f32).round() as i32 as i8);
+        y = y.wrapping_add((beta * x as f32).round() as i32 as i8);
+        x = x.wrapping_add((alpha * y as f32).round() as i32 as i8);
+
+        ((x as u8).wrapping_add(128), (y as u8).wrapping_add(128))
+    })
+}
+
+// One round of a Feistel network with the identity as the round function.
+pub fn xor_mix() -> Map2D {
+    Map2D::from_fn(|(x, y)| (x ^ y, x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_the_generated_maps_are_bijections() {
+        for kind in MapKind::ALL.iter() {
+            for &parameter in [0, 1, 3, 17, 64, 100, 255].iter() {
+                assert!(
+                    kind.map(parameter).is_bijective(),
+                    "{:?} with parameter {}",
+                    kind,
+                    parameter
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn the_cat_map_returns_to_the_identity_after_its_period() {
+        // The cat map's matrix is the square of the Fibonacci matrix, whose period mod 2^k
+        // is 3 * 2^(k - 1), so the cat map's period mod 256 is half of 384.
+        let identity = Map2D::identity();
+        let cat = cat_map();
+
+        let full_period = cat.power(192);
+        let not_quite = cat.power(96);
+
+        let is_identity = |map: &Map2D| {
+            (0..CELL_COUNT).all(|i| map.apply(index_to_cell(i)) == identity.apply(index_to_cell(i)))
+        };
+
+        assert!(is_identity(&full_period));
+        assert!(!is_identity(&not_quite));
+    }
+
+    #[test]
+    fn the_cycle_structure_of_the_cat_map_has_the_right_period() {
+        let cycles = cat_map().cycle_structure().unwrap();
+
+        assert_eq!(cycles.period, Some(192));
+        assert_eq!(
+            cycles.counts.iter().map(|&(l, c)| l * c).sum::<usize>(),
+            CELL_COUNT
+        );
+        // (0, 0) is a fixed point.
+        assert_eq!(cycles.counts[0].0, 1);
+    }
+
+    #[test]
+    fn non_bijections_have_no_cycle_structure() {
+        let squash = Map2D::from_fn(|(x, _)| (x, 0));
+
+        assert_eq!(squash.cycle_structure(), None);
+    }
+
+    #[test]
+    fn inverse_undoes_the_map() {
+        let baker = baker_map();
+        let inverse = baker.inverse().unwrap();
+
+        for i in 0..CELL_COUNT {
+            let cell = index_to_cell(i);
+            assert_eq!(inverse.apply(baker.apply(cell)), cell);
+        }
+    }
+
+    #[test]
+    fn stepping_an_iterated_map_back_and_forth_returns_to_the_start() {
+        let mut iterated = IteratedMap::new(MapKind::Rotation, 10);
+        let cell = (3, 250);
+
+        iterated.step_forward();
+        iterated.step_forward();
+        assert_eq!(
+            iterated.apply(cell),
+            iterated.map.apply(iterated.map.apply(cell))
+        );
+
+        iterated.step_backward();
+        iterated.step_backward();
+        assert_eq!(iterated.iterations, 0);
+        assert_eq!(iterated.apply(cell), cell);
+    }
+
+    #[test]
+    fn quarter_turn_rotations_are_exact() {
+        let quarter = rotation(64);
+
+        assert_eq!(quarter.power(4).apply((10, 200)), (10, 200));
+        assert_ne!(quarter.power(2).apply((10, 200)), (10, 200));
+    }
+}
@@ -0,0 +1,145 @@
+use platform_types::{Button, Input};
+use rendering::{Framebuffer, FONT_ADVANCE, FONT_SIZE, SPRITE_SIZE, WHITE_INDEX, YELLOW_INDEX};
+
+pub struct MenuItem {
+    pub label: &'static str,
+    pub value: String,
+}
+
+// A list of settings, shown over the current mode while open. Which settings are listed
+// is up to the caller, so each mode can have its own.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Menu {
+    pub open: bool,
+    pub selected: usize,
+}
+
+impl Menu {
+    // Select opens and closes the menu. While it is open it uses the arrows, A and B, and
+    // Left and Right change the selected item. The item to change, if any, is returned
+    // along with the input with the buttons the menu used removed.
+    pub fn update(&mut self, item_count: usize, mut input: Input) -> (Option<(usize, i8)>, Input) {
+        let mut change = None;
+
+        // While the menu is closed, B+Select is left for the modes, which use it to switch
+        // the animated axes.
+        let uses_select = self.open || !input.gamepad.contains(Button::B);
+
+        if uses_select && input.pressed_this_frame(Button::Select) {
+            self.open = !self.open;
+        } else if self.open {
+            if input.pressed_this_frame(Button::B) {
+                self.open = false;
+            }
+
+            if item_count > 0 {
+                if input.pressed_this_frame(Button::Up) {
+                    self.selected = (self.selected + item_count - 1) % item_count;
+                }
+                if input.pressed_this_frame(Button::Down) {
+                    self.selected = (self.selected + 1) % item_count;
+                }
+
+                self.selected = self.selected.min(item_count - 1);
+
+                if input.pressed_this_frame(Button::Left) {
+                    change = Some((self.selected, -1));
+                }
+                if input.pressed_this_frame(Button::Right) || input.pressed_this_frame(Button::A) {
+                    change = Some((self.selected, 1));
+                }
+            }
+        }
+
+        let mut used = Button::Ty::empty();
+        if uses_select {
+            used.insert(Button::Select);
+        }
+        if self.open {
+            used.insert(Button::Up | Button::Down | Button::Left | Button::Right);
+            used.insert(Button::A | Button::B);
+        }
+        input.gamepad.remove(used);
+        input.previous_gamepad.remove(used);
+
+        (change, input)
+    }
+
+    pub fn render(&self, framebuffer: &mut Framebuffer, items: &[MenuItem]) {
+        if !self.open {
+            return;
+        }
+
+        let longest = items
+            .iter()
+            .map(|item| item.label.len() + item.value.len() + 3)
+            .max()
+            .unwrap_or(0);
+
+        let x = SPRITE_SIZE as usize;
+        let y = SPRITE_SIZE as usize;
+        let w = longest * FONT_ADVANCE as usize + SPRITE_SIZE as usize * 2;
+        let h = items.len() * FONT_SIZE as usize + SPRITE_SIZE as usize * 2;
+
+        framebuffer.window(x, y, w, h);
+
+        let mut line_y = y + SPRITE_SIZE as usize;
+        for (i, item) in items.iter().enumerate() {
+            let (marker, colour) = if i == self.selected {
+                (">", YELLOW_INDEX)
+            } else {
+                (" ", WHITE_INDEX)
+            };
+
+            framebuffer.print_line(
+                format!("{}{}: {}", marker, item.label, item.value).as_bytes(),
+                x + SPRITE_SIZE as usize,
+                line_y,
+                colour,
+            );
+            line_y += FONT_SIZE as usize;
+        }
+    }
+}
+
+// Steps through `all` by `delta`, wrapping around at either end.
+pub fn cycle<T: Copy + PartialEq>(all: &[T], current: T, delta: i8) -> T {
+    let len = all.len() as isize;
+    let index = all.iter().position(|&t| t == current).unwrap_or(0) as isize;
+
+    all[(index + delta as isize).rem_euclid(len) as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycle_wraps_in_both_directions() {
+        let all = [1, 2, 3];
+
+        assert_eq!(cycle(&all, 3, 1), 1);
+        assert_eq!(cycle(&all, 1, -1), 3);
+        assert_eq!(cycle(&all, 2, 1), 3);
+    }
+
+    #[test]
+    fn menu_reports_changes_to_the_selected_item() {
+        let mut menu = Menu {
+            open: true,
+            selected: 0,
+        };
+
+        let mut input = Input::new();
+        input.gamepad.insert(Button::Down);
+        let (change, _) = menu.update(2, input);
+        assert_eq!(change, None);
+        assert_eq!(menu.selected, 1);
+
+        let mut input = Input::new();
+        input.gamepad.insert(Button::Left);
+        let (change, input) = menu.update(2, input);
+        assert_eq!(change, Some((1, -1)));
+        assert!(!input.gamepad.contains(Button::Left));
+    }
+}
@@ -0,0 +1,289 @@
+use crate::boolean::{walsh_hadamard_transform, BooleanFunction};
+use crate::game::Func;
+
+// Some well known 8-bit S-boxes. Each one is indexed by the input byte, and the outputs are
+// bytes reinterpreted as `i8`s, so they can be viewed like any other `Func`.
+
+// The AES (Rijndael) S-box: inversion in GF(2^8) followed by an affine transformation.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+pub const AES: Func = [
+    99, 124, 119, 123, -14, 107, 111, -59, 48, 1, 103, 43, -2, -41, -85, 118,
+    -54, -126, -55, 125, -6, 89, 71, -16, -83, -44, -94, -81, -100, -92, 114, -64,
+    -73, -3, -109, 38, 54, 63, -9, -52, 52, -91, -27, -15, 113, -40, 49, 21,
+    4, -57, 35, -61, 24, -106, 5, -102, 7, 18, -128, -30, -21, 39, -78, 117,
+    9, -125, 44, 26, 27, 110, 90, -96, 82, 59, -42, -77, 41, -29, 47, -124,
+    83, -47, 0, -19, 32, -4, -79, 91, 106, -53, -66, 57, 74, 76, 88, -49,
+    -48, -17, -86, -5, 67, 77, 51, -123, 69, -7, 2, 127, 80, 60, -97, -88,
+    81, -93, 64, -113, -110, -99, 56, -11, -68, -74, -38, 33, 16, -1, -13, -46,
+    -51, 12, 19, -20, 95, -105, 68, 23, -60, -89, 126, 61, 100, 93, 25, 115,
+    96, -127, 79, -36, 34, 42, -112, -120, 70, -18, -72, 20, -34, 94, 11, -37,
+    -32, 50, 58, 10, 73, 6, 36, 92, -62, -45, -84, 98, -111, -107, -28, 121,
+    -25, -56, 55, 109, -115, -43, 78, -87, 108, 86, -12, -22, 101, 122, -82, 8,
+    -70, 120, 37, 46, 28, -90, -76, -58, -24, -35, 116, 31, 75, -67, -117, -118,
+    112, 62, -75, 102, 72, 3, -10, 14, 97, 53, 87, -71, -122, -63, 29, -98,
+    -31, -8, -104, 17, 105, -39, -114, -108, -101, 30, -121, -23, -50, 85, 40, -33,
+    -116, -95, -119, 13, -65, -26, 66, 104, 65, -103, 45, 15, -80, 84, -69, 22,
+];
+
+// The inverse of the AES S-box, used when decrypting.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+pub const AES_INVERSE: Func = [
+    82, 9, 106, -43, 48, 54, -91, 56, -65, 64, -93, -98, -127, -13, -41, -5,
+    124, -29, 57, -126, -101, 47, -1, -121, 52, -114, 67, 68, -60, -34, -23, -53,
+    84, 123, -108, 50, -90, -62, 35, 61, -18, 76, -107, 11, 66, -6, -61, 78,
+    8, 46, -95, 102, 40, -39, 36, -78, 118, 91, -94, 73, 109, -117, -47, 37,
+    114, -8, -10, 100, -122, 104, -104, 22, -44, -92, 92, -52, 93, 101, -74, -110,
+    108, 112, 72, 80, -3, -19, -71, -38, 94, 21, 70, 87, -89, -115, -99, -124,
+    -112, -40, -85, 0, -116, -68, -45, 10, -9, -28, 88, 5, -72, -77, 69, 6,
+    -48, 44, 30, -113, -54, 63, 15, 2, -63, -81, -67, 3, 1, 19, -118, 107,
+    58, -111, 17, 65, 79, 103, -36, -22, -105, -14, -49, -50, -16, -76, -26, 115,
+    -106, -84, 116, 34, -25, -83, 53, -123, -30, -7, 55, -24, 28, 117, -33, 110,
+    71, -15, 26, 113, 29, 41, -59, -119, 111, -73, 98, 14, -86, 24, -66, 27,
+    -4, 86, 62, 75, -58, -46, 121, 32, -102, -37, -64, -2, 120, -51, 90, -12,
+    31, -35, -88, 51, -120, 7, -57, 49, -79, 18, 16, 89, 39, -128, -20, 95,
+    96, 81, 127, -87, 25, -75, 74, 13, 45, -27, 122, -97, -109, -55, -100, -17,
+    -96, -32, 59, 77, -82, 42, -11, -80, -56, -21, -69, 60, -125, 83, -103, 97,
+    23, 43, 4, 126, -70, 119, -42, 38, -31, 105, 20, 99, 85, 33, 12, 125,
+];
+
+// Plain inversion in GF(2^8), with 0 mapped to 0, as proposed by Nyberg. This is the AES
+// S-box without the affine part, so it has the same DDT and LAT up to permutation.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+pub const INVERSION: Func = [
+    0, 1, -115, -10, -53, 82, 123, -47, -24, 79, 41, -64, -80, -31, -27, -57,
+    116, -76, -86, 75, -103, 43, 96, 95, 88, 63, -3, -52, -1, 64, -18, -78,
+    58, 110, 90, -15, 85, 77, -88, -55, -63, 10, -104, 21, 48, 68, -94, -62,
+    44, 69, -110, 108, -13, 57, 102, 66, -14, 53, 32, 111, 119, -69, 89, 25,
+    29, -2, 55, 103, 45, 49, -11, 105, -89, 100, -85, 19, 84, 37, -23, 9,
+    -19, 92, 5, -54, 76, 36, -121, -65, 24, 62, 34, -16, 81, -20, 97, 23,
+    22, 94, -81, -45, 73, -90, 54, 67, -12, 71, -111, -33, 51, -109, 33, 59,
+    121, -73, -105, -123, 16, -75, -70, 60, -74, 112, -48, 6, -95, -6, -127, -126,
+    -125, 126, 127, -128, -106, 115, -66, 86, -101, -98, -107, -39, -9, 2, -71, -92,
+    -34, 106, 50, 109, -40, -118, -124, 114, 42, 20, -97, -120, -7, -36, -119, -102,
+    -5, 124, 46, -61, -113, -72, 101, 72, 38, -56, 18, 74, -50, -25, -46, 98,
+    12, -32, 31, -17, 17, 117, 120, 113, -91, -114, 118, 61, -67, -68, -122, 87,
+    11, 40, 47, -93, -38, -44, -28, 15, -87, 39, 83, 4, 27, -4, -84, -26,
+    122, 7, -82, 99, -59, -37, -30, -22, -108, -117, -60, -43, -99, -8, -112, 107,
+    -79, 13, -42, -21, -58, 14, -49, -83, 8, 78, -41, -29, 93, 80, 30, -77,
+    91, 35, 56, 52, 104, 70, 3, -116, -35, -100, 125, -96, -51, 26, 65, 28,
+];
+
+// x^3 in GF(2^8). This is almost perfect nonlinear, that is no difference occurs more
+// than twice, but since 3 divides 255 it is not a permutation.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+pub const CUBE: Func = [
+    0, 1, 8, 15, 64, 85, 120, 107, 54, 127, -98, -47, -19, -80, 117, 46,
+    -85, -95, -43, -39, -100, -126, -46, -54, 41, 107, -9, -77, -123, -45, 107, 59,
+    47, 98, 127, 52, -14, -85, -110, -51, -116, -119, 124, 127, -54, -37, 10, 29,
+    83, 21, 117, 53, -7, -85, -17, -69, 68, 74, -62, -54, 117, 111, -61, -33,
+    99, -119, 61, -47, -43, 43, -69, 67, -47, 115, 47, -117, -4, 74, 50, -126,
+    12, -19, 36, -61, -51, 56, -43, 38, 10, -93, -126, 45, 80, -19, -24, 83,
+    -82, 8, -88, 8, -123, 55, -77, 7, -119, 103, 47, -57, 57, -61, -81, 83,
+    22, -69, 102, -51, 74, -13, 10, -75, -123, 96, 85, -74, 66, -77, -94, 85,
+    53, 46, 36, 57, -13, -4, -46, -37, -14, -95, 67, 22, -81, -24, 46, 111,
+    -46, -62, -75, -93, 99, 103, 52, 54, -95, -7, 102, 56, -117, -57, 124, 54,
+    96, 55, 41, 120, 59, 120, 66, 7, 50, 45, -37, -62, -14, -7, 43, 38,
+    80, 12, 111, 53, 124, 52, 115, 61, -74, -94, 41, 59, 1, 1, -82, -88,
+    7, -9, 64, -74, 55, -45, 64, -94, 68, -4, -93, 29, -17, 67, 56, -110,
+    36, -33, 21, -24, 99, -116, 98, -117, -45, 96, 66, -9, 15, -88, -82, 15,
+    -80, 12, -81, 21, 29, -75, 50, -100, 102, -110, -39, 43, 80, -80, -33, 57,
+    68, -13, 45, -100, -98, 61, -57, 98, 38, -39, -17, 22, 103, -116, -98, 115,
+];
+
+// The 4-bit S-box from the PRESENT block cipher applied to each half of the byte. Using
+// small S-boxes side by side like this is much weaker than a real 8-bit S-box.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+pub const PRESENT_PAIR: Func = [
+    -52, -59, -58, -53, -55, -64, -54, -51, -61, -50, -49, -56, -60, -57, -63, -62,
+    92, 85, 86, 91, 89, 80, 90, 93, 83, 94, 95, 88, 84, 87, 81, 82,
+    108, 101, 102, 107, 105, 96, 106, 109, 99, 110, 111, 104, 100, 103, 97, 98,
+    -68, -75, -74, -69, -71, -80, -70, -67, -77, -66, -65, -72, -76, -73, -79, -78,
+    -100, -107, -106, -101, -103, -112, -102, -99, -109, -98, -97, -104, -108, -105, -111, -110,
+    12, 5, 6, 11, 9, 0, 10, 13, 3, 14, 15, 8, 4, 7, 1, 2,
+    -84, -91, -90, -85, -87, -96, -86, -83, -93, -82, -81, -88, -92, -89, -95, -94,
+    -36, -43, -42, -37, -39, -48, -38, -35, -45, -34, -33, -40, -44, -41, -47, -46,
+    60, 53, 54, 59, 57, 48, 58, 61, 51, 62, 63, 56, 52, 55, 49, 50,
+    -20, -27, -26, -21, -23, -32, -22, -19, -29, -18, -17, -24, -28, -25, -31, -30,
+    -4, -11, -10, -5, -7, -16, -6, -3, -13, -2, -1, -8, -12, -9, -15, -14,
+    -116, -123, -122, -117, -119, -128, -118, -115, -125, -114, -113, -120, -124, -121, -127, -126,
+    76, 69, 70, 75, 73, 64, 74, 77, 67, 78, 79, 72, 68, 71, 65, 66,
+    124, 117, 118, 123, 121, 112, 122, 125, 115, 126, 127, 120, 116, 119, 113, 114,
+    28, 21, 22, 27, 25, 16, 26, 29, 19, 30, 31, 24, 20, 23, 17, 18,
+    44, 37, 38, 43, 41, 32, 42, 45, 35, 46, 47, 40, 36, 39, 33, 34,
+];
+
+pub const LIBRARY: [(&str, Func); 5] = [
+    ("aes", AES),
+    ("aes inverse", AES_INVERSE),
+    ("inversion", INVERSION),
+    ("cube", CUBE),
+    ("present pair", PRESENT_PAIR),
+];
+
+pub fn library_index(func: &Func) -> Option<usize> {
+    LIBRARY.iter().position(|(_, sbox)| sbox[..] == func[..])
+}
+
+#[inline]
+fn output(func: &Func, x: usize) -> usize {
+    func[x] as u8 as usize
+}
+
+#[inline]
+fn parity(x: usize) -> bool {
+    x.count_ones() & 1 == 1
+}
+
+// `table[a][b]` is the number of inputs `x` where `S(x ^ a) ^ S(x) == b`.
+pub type DifferenceTable = [[u16; 256]; 256];
+
+// `table[a][b]` is the number of inputs `x` where the parity of `a & x` matches the parity
+// of `b & S(x)`, minus 128. So approximations that hold exactly half the time are 0.
+pub type LinearTable = [[i16; 256]; 256];
+
+pub fn difference_distribution_table(func: &Func) -> Box<DifferenceTable> {
+    let mut table = Box::new([[0; 256]; 256]);
+
+    for a in 0..256 {
+        for x in 0..256 {
+            let b = output(func, x ^ a) ^ output(func, x);
+            table[a][b] += 1;
+        }
+    }
+
+    table
+}
+
+pub fn linear_approximation_table(func: &Func) -> Box<LinearTable> {
+    let mut table = Box::new([[0; 256]; 256]);
+
+    // Each column is half the Walsh spectrum of `x -> parity(b & S(x))`, so a fast
+    // Walsh-Hadamard transform gets it without trying every input mask separately.
+    let mut spectrum = [0i16; 256];
+    for b in 0..256 {
+        for (x, w) in spectrum.iter_mut().enumerate() {
+            *w = if parity(b & output(func, x)) { -1 } else { 1 };
+        }
+
+        walsh_hadamard_transform(&mut spectrum);
+
+        for a in 0..256 {
+            table[a][b] = spectrum[a] / 2;
+        }
+    }
+
+    table
+}
+
+// The largest entry outside the trivial `a == 0` row. Lower is better; 2 is the minimum.
+pub fn differential_uniformity(table: &DifferenceTable) -> u16 {
+    table[1..]
+        .iter()
+        .flat_map(|row| row.iter())
+        .cloned()
+        .max()
+        .unwrap_or(0)
+}
+
+// The distance from the closest affine function over all the non-trivial output masks.
+// Higher is better; 112 is the best known for 8-bit permutations.
+pub fn nonlinearity(table: &LinearTable) -> u16 {
+    let largest_bias = table
+        .iter()
+        .flat_map(|row| row[1..].iter())
+        .map(|&bias| bias.unsigned_abs())
+        .max()
+        .unwrap_or(0);
+
+    128 - largest_bias
+}
+
+// The highest degree of any monomial in the algebraic normal forms of the output bits.
+pub fn algebraic_degree(func: &Func) -> u32 {
+    (0..8)
+        .map(|bit| BooleanFunction::output_bit(func, bit).degree())
+        .max()
+        .unwrap_or(0)
+}
+
+// Everything the analysis modes show, kept around so it is only worked out again when the
+// `Func` changes.
+#[derive(Clone)]
+pub struct SBoxAnalysis {
+    pub func: Func,
+    pub ddt: Box<DifferenceTable>,
+    pub lat: Box<LinearTable>,
+    pub differential_uniformity: u16,
+    pub nonlinearity: u16,
+    pub algebraic_degree: u32,
+}
+
+impl SBoxAnalysis {
+    pub fn new(func: &Func) -> Self {
+        let ddt = difference_distribution_table(func);
+        let lat = linear_approximation_table(func);
+
+        SBoxAnalysis {
+            func: *func,
+            differential_uniformity: differential_uniformity(&ddt),
+            nonlinearity: nonlinearity(&lat),
+            algebraic_degree: algebraic_degree(func),
+            ddt,
+            lat,
+        }
+    }
+
+    pub fn is_for(&self, func: &Func) -> bool {
+        self.func[..] == func[..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aes_has_the_published_properties() {
+        assert_eq!(AES[0] as u8, 0x63);
+        assert_eq!(AES_INVERSE[0x63] as u8, 0);
+
+        let analysis = SBoxAnalysis::new(&AES);
+
+        assert_eq!(analysis.differential_uniformity, 4);
+        assert_eq!(analysis.nonlinearity, 112);
+        assert_eq!(analysis.algebraic_degree, 7);
+    }
+
+    #[test]
+    fn aes_inverse_undoes_aes() {
+        for x in 0..256 {
+            assert_eq!(output(&AES_INVERSE, output(&AES, x)), x);
+        }
+    }
+
+    #[test]
+    fn cube_is_almost_perfect_nonlinear() {
+        let ddt = difference_distribution_table(&CUBE);
+
+        assert_eq!(differential_uniformity(&ddt), 2);
+        assert_eq!(algebraic_degree(&CUBE), 2);
+    }
+
+    #[test]
+    fn tables_match_their_definitions() {
+        let ddt = difference_distribution_table(&PRESENT_PAIR);
+        let lat = linear_approximation_table(&PRESENT_PAIR);
+
+        for &(a, b) in &[(0, 0), (1, 3), (0x10, 0x30), (0x5a, 0xc3), (0xff, 0x01)] {
+            let differences = (0..256)
+                .filter(|&x| output(&PRESENT_PAIR, x ^ a) ^ output(&PRESENT_PAIR, x) == b)
+                .count();
+            assert_eq!(ddt[a][b] as usize, differences);
+
+            let agreements = (0..256)
+                .filter(|&x| parity(a & x) == parity(b & output(&PRESENT_PAIR, x)))
+                .count();
+            assert_eq!(lat[a][b] as isize, agreements as isize - 128);
+        }
+    }
+}
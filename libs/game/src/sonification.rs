@@ -0,0 +1,94 @@
+use crate::game::{Func, Func2};
+
+use audio::{note_frequency, synthesise_wavetable, WavetableOscillator, DEFAULT_SAMPLE_RATE};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WavetableSource {
+    #[default]
+    Func,
+    // The row of `Func2` selected by `x_offset`.
+    Func2Row,
+}
+
+impl WavetableSource {
+    pub const ALL: [WavetableSource; 2] = [WavetableSource::Func, WavetableSource::Func2Row];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WavetableSource::Func => "func",
+            WavetableSource::Func2Row => "func2 row",
+        }
+    }
+}
+
+pub const MIN_NOTE: u8 = 24;
+pub const MAX_NOTE: u8 = 96;
+
+const VOLUME: f32 = 0.25;
+
+const WAV_SECONDS: f32 = 2.0;
+
+// Plays one of the functions as a single cycle waveform, for as long as `playing` is set,
+// whichever mode is showing.
+#[derive(Clone, Copy, Debug)]
+pub struct Sonification {
+    pub playing: bool,
+    pub note: u8,
+    pub source: WavetableSource,
+    pub oscillator: WavetableOscillator,
+}
+
+impl Default for Sonification {
+    fn default() -> Self {
+        Sonification {
+            playing: false,
+            // A3, which is 220Hz.
+            note: 57,
+            source: Default::default(),
+            oscillator: WavetableOscillator::new(),
+        }
+    }
+}
+
+impl Sonification {
+    pub fn table<'a>(&self, func: &'a Func, func2: &'a Func2, row: usize) -> &'a [i8; 256] {
+        match self.source {
+            WavetableSource::Func => func,
+            WavetableSource::Func2Row => &func2[row & 0xFF],
+        }
+    }
+
+    pub fn change_note(&mut self, delta: i8) {
+        self.note = (self.note as i16 + delta as i16)
+            .max(MIN_NOTE as i16)
+            .min(MAX_NOTE as i16) as u8;
+    }
+
+    // Called by the platform whenever it needs more samples. When stopped this keeps going
+    // until it has faded out.
+    pub fn fill(&mut self, table: &[i8; 256], sample_rate: u32, out: &mut [f32]) {
+        if !self.playing && self.oscillator.is_silent() {
+            for sample in out.iter_mut() {
+                *sample = 0.0;
+            }
+            return;
+        }
+
+        let volume = if self.playing { VOLUME } else { 0.0 };
+        self.oscillator
+            .render(table, note_frequency(self.note), sample_rate, volume, out);
+    }
+
+    // A couple of seconds of the table at the current note, whether or not it is playing.
+    pub fn wav(&self, table: &[i8; 256]) -> Vec<u8> {
+        let samples = synthesise_wavetable(
+            table,
+            note_frequency(self.note),
+            DEFAULT_SAMPLE_RATE,
+            VOLUME,
+            WAV_SECONDS,
+        );
+
+        export::encode_wav(&samples, DEFAULT_SAMPLE_RATE)
+    }
+}
@@ -0,0 +1,31 @@
+use platform_types::{Envelope, Waveform, SFX};
+
+// A short rising blip.
+pub const MODE_CHANGE: SFX = SFX {
+    waveform: Waveform::Square(0.25),
+    start_frequency: 440.0,
+    end_frequency: 880.0,
+    envelope: Envelope {
+        attack: 0.005,
+        decay: 0.04,
+        sustain_level: 0.5,
+        sustain: 0.03,
+        release: 0.05,
+    },
+    volume: 0.2,
+};
+
+// A burst of noise that falls away, like something being shaken up.
+pub const RANDOMISE: SFX = SFX {
+    waveform: Waveform::Noise,
+    start_frequency: 6000.0,
+    end_frequency: 400.0,
+    envelope: Envelope {
+        attack: 0.005,
+        decay: 0.08,
+        sustain_level: 0.4,
+        sustain: 0.05,
+        release: 0.15,
+    },
+    volume: 0.2,
+};
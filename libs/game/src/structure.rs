@@ -0,0 +1,242 @@
+use crate::game::Func2;
+
+// Treats a `Func2` as a binary operation `a * b` on the 256 possible bytes, where `a`
+// picks the row and `b` the column.
+#[inline]
+pub fn operate(func: &Func2, a: u8, b: u8) -> u8 {
+    func[a as usize][b as usize] as u8
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Property {
+    #[default]
+    Commutativity,
+    Associativity,
+    Latin,
+    Idempotence,
+}
+
+impl Property {
+    pub const ALL: [Property; 4] = [
+        Property::Commutativity,
+        Property::Associativity,
+        Property::Latin,
+        Property::Idempotence,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Property::Commutativity => "commutativity",
+            Property::Associativity => "associativity",
+            Property::Latin => "latin square",
+            Property::Idempotence => "idempotence",
+        }
+    }
+}
+
+// Whether the cell at row `a` and column `b` breaks `property`. Associativity involves a
+// third element, so that one is checked with `c` on the right.
+pub fn fails_at(func: &Func2, property: Property, (a, b): (u8, u8), c: u8) -> bool {
+    match property {
+        Property::Commutativity => operate(func, a, b) != operate(func, b, a),
+        Property::Associativity => {
+            operate(func, operate(func, a, b), c) != operate(func, a, operate(func, b, c))
+        }
+        Property::Latin => {
+            let value = operate(func, a, b);
+            (0..=255u8).any(|other| {
+                (other != b && operate(func, a, other) == value)
+                    || (other != a && operate(func, other, b) == value)
+            })
+        }
+        Property::Idempotence => a == b && operate(func, a, a) != a,
+    }
+}
+
+// The whole table for `fails_at`, indexed by `a * 256 + b`. Doing the rows and columns
+// all at once keeps the latin square check from being quadratic per cell.
+pub fn failures(func: &Func2, property: Property, c: u8) -> Vec<bool> {
+    if property != Property::Latin {
+        let mut failures = Vec::with_capacity(256 * 256);
+        for a in 0..=255 {
+            for b in 0..=255 {
+                failures.push(fails_at(func, property, (a, b), c));
+            }
+        }
+        return failures;
+    }
+
+    let mut row_counts = vec![[0u16; 256]; 256];
+    let mut column_counts = vec![[0u16; 256]; 256];
+    for a in 0..256 {
+        for b in 0..256 {
+            let value = func[a][b] as u8 as usize;
+            row_counts[a][value] += 1;
+            column_counts[b][value] += 1;
+        }
+    }
+
+    let mut failures = Vec::with_capacity(256 * 256);
+    for a in 0..256 {
+        for b in 0..256 {
+            let value = func[a][b] as u8 as usize;
+            failures.push(row_counts[a][value] > 1 || column_counts[b][value] > 1);
+        }
+    }
+    failures
+}
+
+// The properties of the whole operation that do not depend on anything else, worked out
+// once since checking associativity means trying every triple.
+#[derive(Clone)]
+pub struct StructureAnalysis {
+    pub func: Box<Func2>,
+    pub commutativity_counterexample: Option<(u8, u8)>,
+    pub associativity_counterexample: Option<(u8, u8, u8)>,
+    pub identity: Option<u8>,
+    pub absorbing: Option<u8>,
+    // Only counted when there is an identity, since otherwise inverses mean nothing.
+    pub invertible_count: usize,
+    pub idempotent_count: usize,
+    pub is_latin_square: bool,
+}
+
+impl StructureAnalysis {
+    pub fn new(func: &Func2) -> Self {
+        let all = || 0..=255u8;
+
+        let commutativity_counterexample = all()
+            .flat_map(|a| (a..=255).map(move |b| (a, b)))
+            .find(|&(a, b)| operate(func, a, b) != operate(func, b, a));
+
+        let associativity_counterexample = all()
+            .flat_map(|a| all().flat_map(move |b| all().map(move |c| (a, b, c))))
+            .find(|&(a, b, c)| {
+                operate(func, operate(func, a, b), c) != operate(func, a, operate(func, b, c))
+            });
+
+        let identity =
+            all().find(|&e| all().all(|x| operate(func, e, x) == x && operate(func, x, e) == x));
+
+        let absorbing =
+            all().find(|&z| all().all(|x| operate(func, z, x) == z && operate(func, x, z) == z));
+
+        let invertible_count = match identity {
+            Some(e) => all()
+                .filter(|&x| all().any(|y| operate(func, x, y) == e && operate(func, y, x) == e))
+                .count(),
+            None => 0,
+        };
+
+        let idempotent_count = all().filter(|&x| operate(func, x, x) == x).count();
+
+        let is_latin_square = !failures(func, Property::Latin, 0).contains(&true);
+
+        StructureAnalysis {
+            func: Box::new(*func),
+            commutativity_counterexample,
+            associativity_counterexample,
+            identity,
+            absorbing,
+            invertible_count,
+            idempotent_count,
+            is_latin_square,
+        }
+    }
+
+    pub fn is_for(&self, func: &Func2) -> bool {
+        self.func
+            .iter()
+            .zip(func.iter())
+            .all(|(r1, r2)| r1[..] == r2[..])
+    }
+
+    pub fn is_commutative(&self) -> bool {
+        self.commutativity_counterexample.is_none()
+    }
+
+    pub fn is_associative(&self) -> bool {
+        self.associativity_counterexample.is_none()
+    }
+
+    // The most specific name that fits, from the usual hierarchy.
+    pub fn classification(&self) -> &'static str {
+        let has_identity = self.identity.is_some();
+
+        match (self.is_associative(), has_identity, self.is_latin_square) {
+            // An associative quasigroup always has an identity and inverses.
+            (true, _, true) if self.is_commutative() => "abelian group",
+            (true, _, true) => "group",
+            (false, true, true) => "loop",
+            (false, false, true) => "quasigroup",
+            (true, true, false) => "monoid",
+            (true, false, false) => "semigroup",
+            (false, _, false) => "magma",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table<F: Fn(u8, u8) -> u8>(op: F) -> Box<Func2> {
+        let mut func = Box::new([[0; 256]; 256]);
+        for a in 0..=255 {
+            for b in 0..=255 {
+                func[a as usize][b as usize] = op(a, b) as i8;
+            }
+        }
+        func
+    }
+
+    #[test]
+    fn addition_is_an_abelian_group() {
+        let analysis = StructureAnalysis::new(&table(u8::wrapping_add));
+
+        assert_eq!(analysis.classification(), "abelian group");
+        assert_eq!(analysis.identity, Some(0));
+        assert_eq!(analysis.absorbing, None);
+        assert_eq!(analysis.invertible_count, 256);
+        assert_eq!(analysis.idempotent_count, 1);
+    }
+
+    #[test]
+    fn subtraction_has_real_counterexamples() {
+        let func = table(u8::wrapping_sub);
+        let analysis = StructureAnalysis::new(&func);
+
+        assert_eq!(analysis.classification(), "quasigroup");
+
+        let (a, b) = analysis.commutativity_counterexample.unwrap();
+        assert!(fails_at(&func, Property::Commutativity, (a, b), 0));
+
+        let (a, b, c) = analysis.associativity_counterexample.unwrap();
+        assert!(fails_at(&func, Property::Associativity, (a, b), c));
+    }
+
+    #[test]
+    fn max_is_a_commutative_monoid_with_an_absorbing_element() {
+        let analysis = StructureAnalysis::new(&table(std::cmp::max));
+
+        assert_eq!(analysis.classification(), "monoid");
+        assert!(analysis.is_commutative());
+        assert_eq!(analysis.identity, Some(0));
+        assert_eq!(analysis.absorbing, Some(255));
+        assert_eq!(analysis.invertible_count, 1);
+        assert_eq!(analysis.idempotent_count, 256);
+    }
+
+    #[test]
+    fn latin_failures_match_fails_at() {
+        let func = table(|a, b| a.wrapping_mul(b));
+        let failures = failures(&func, Property::Latin, 0);
+
+        for &(a, b) in &[(0, 0), (1, 1), (2, 3), (3, 5), (255, 128)] {
+            assert_eq!(
+                failures[a as usize * 256 + b as usize],
+                fails_at(&func, Property::Latin, (a, b), 0)
+            );
+        }
+    }
+}
@@ -1,10 +1,23 @@
 #[macro_use]
 extern crate bitflags;
 
+use std::error::Error;
+
+// A mouse, or similar, over the screen. The position is in framebuffer pixels, so it can
+// be past the grid when the framebuffer is larger.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct Pointer {
+    pub x: usize,
+    pub y: usize,
+    pub pressed: bool,
+}
+
 #[derive(Clone, Copy, Default, Debug)]
 pub struct Input {
     pub gamepad: Button::Ty,
     pub previous_gamepad: Button::Ty,
+    // `None` when there is no pointer, or it is off the screen.
+    pub pointer: Option<Pointer>,
 }
 
 impl Input {
@@ -12,6 +25,7 @@ impl Input {
         Input {
             gamepad: Button::Ty::empty(),
             previous_gamepad: Button::Ty::empty(),
+            pointer: None,
         }
     }
 
@@ -24,32 +38,46 @@ impl Input {
     }
 }
 
-//TODO more meaningful names for these?
-//TODO clear out unused sound effects
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Waveform {
+    Sine,
+    Triangle,
+    Sawtooth,
+    // The fraction of each cycle spent high, from 0.0 to 1.0.
+    Square(f32),
+    // A new random level each cycle, so the frequency still changes how it sounds.
+    Noise,
+}
 
-#[derive(Clone, Copy, Debug)]
-pub enum SFX {
-    CardPlace,
-    CardSlide,
-    ChipsCollide,
-    DieShuffle,
-    DieThrow,
-    ButtonPress,
+// The times are in seconds, and `sustain_level` is a fraction of the full volume, which is
+// held for `sustain` seconds before the release starts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Envelope {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain_level: f32,
+    pub sustain: f32,
+    pub release: f32,
 }
 
-impl SFX {
-    pub fn to_sound_key(&self) -> &'static str {
-        match *self {
-            SFX::CardPlace => "cardPlace",
-            SFX::CardSlide => "cardSlide",
-            SFX::ChipsCollide => "chipsCollide",
-            SFX::DieShuffle => "dieShuffle",
-            SFX::DieThrow => "dieThrow",
-            SFX::ButtonPress => "buttonPress",
-        }
+impl Envelope {
+    pub fn duration(&self) -> f32 {
+        self.attack + self.decay + self.sustain + self.release
     }
 }
 
+// The parameters to synthesise a sound effect from, so the platforms don't need any
+// recordings. The pitch sweeps from `start_frequency` to `end_frequency` over the whole
+// effect, which can be the same for a steady pitch.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SFX {
+    pub waveform: Waveform,
+    pub start_frequency: f32,
+    pub end_frequency: f32,
+    pub envelope: Envelope,
+    pub volume: f32,
+}
+
 pub struct Speaker {
     requests: Vec<SFX>,
 }
@@ -71,11 +99,12 @@ impl Speaker {
 }
 
 // These values are deliberately picked to be the same as the ones in NES' input registers.
+// The buttons past the first eight are the extra ones on a SNES controller.
 #[allow(non_snake_case)]
 pub mod Button {
     bitflags! {
         #[derive(Default)]
-        pub flags Ty: u8 {
+        pub flags Ty: u16 {
             const A          = 1 << 0,
             const B          = 1 << 1,
             const Select     = 1 << 2,
@@ -83,14 +112,37 @@ pub mod Button {
             const Up         = 1 << 4,
             const Down       = 1 << 5,
             const Left       = 1 << 6,
-            const Right      = 1 << 7
+            const Right      = 1 << 7,
+            const L          = 1 << 8,
+            const R          = 1 << 9,
+            const X          = 1 << 10,
+            const Y          = 1 << 11
         }
     }
 }
 
+// Things past the buttons and the pointer, which the platform passes along without knowing
+// what they mean, so what each one does is up to the state.
+#[derive(Clone, Copy, Debug)]
+pub enum Command<'a> {
+    // A key that isn't one of the buttons, by the name the platform gives it, like "u".
+    Key(&'a str),
+    // A file the user opened, or dropped onto the page.
+    File { name: &'a str, bytes: &'a [u8] },
+}
+
+// A file for the platform to save, or offer as a download.
+#[derive(Clone, Debug)]
+pub struct Export {
+    pub file_name: &'static str,
+    pub mime_type: &'static str,
+    pub bytes: Vec<u8>,
+}
+
 pub type Logger = Option<fn(&str) -> ()>;
 
-pub type StateParams = ([u8; 16], Logger, Logger);
+// The last one is the size of the framebuffer, which is never smaller than the grid.
+pub type StateParams = ([u8; 16], Logger, Logger, (usize, usize));
 
 pub trait State {
     fn frame(&mut self, handle_sound: fn(SFX));
@@ -99,5 +151,30 @@ pub trait State {
 
     fn release(&mut self, button: Button::Ty);
 
+    fn move_pointer(&mut self, pointer: Option<Pointer>);
+
     fn get_frame_buffer(&self) -> &[u32];
+
+    fn get_frame_buffer_dimensions(&self) -> (usize, usize);
+
+    // Returns whether the command did anything, so the platform can let unused keys through.
+    fn handle_command(&mut self, command: Command) -> Result<bool, Box<dyn Error>>;
+
+    // Returns `None` if `key` doesn't export anything.
+    fn export(&mut self, key: &str) -> Option<Result<Export, Box<dyn Error>>>;
+
+    // Exports that take too long to make in one go are started by a command and made over
+    // several frames. Once one is done it comes out here, so the platform should check
+    // after every frame.
+    fn finished_export(&mut self) -> Option<Result<Export, Box<dyn Error>>> {
+        None
+    }
+
+    // Overwrites `samples` with the next mono samples to play, from -1.0 to 1.0. This is
+    // pulled whenever the platform's audio needs more, rather than once a frame.
+    fn fill_audio(&mut self, _sample_rate: u32, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            *sample = 0.0;
+        }
+    }
 }
@@ -0,0 +1,239 @@
+use crate::constants::{FONT_ADVANCE, PALETTE, WHITE, WHITE_INDEX};
+use crate::rendering::{rgb_to_colour, Framebuffer};
+
+// Ways of turning a number from 0.0 to 1.0 into a colour. Apart from `Cyclic`, values
+// outside that range are clamped to it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Ramp {
+    // Dark purple through blue and green to yellow, getting steadily brighter, like
+    // matplotlib's viridis.
+    #[default]
+    Viridis,
+    Greyscale,
+    // Blue through a light grey to red, for values where the middle means zero.
+    Diverging,
+    // Wraps around so 0.0 and 1.0 are the same colour, for things like angles.
+    Cyclic,
+}
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const VIRIDIS: [(u8, u8, u8); 9] = [
+    (68, 1, 84), (71, 44, 122), (59, 81, 139), (44, 113, 142), (33, 144, 141),
+    (39, 173, 129), (92, 200, 99), (170, 220, 50), (253, 231, 37),
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const DIVERGING: [(u8, u8, u8); 5] = [
+    (59, 76, 192), (141, 176, 254), (221, 221, 221), (244, 154, 123), (180, 4, 38),
+];
+
+// Like matplotlib's twilight. The first and last stops are the same, so it wraps.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const CYCLIC: [(u8, u8, u8); 11] = [
+    (226, 217, 226), (158, 187, 201), (107, 139, 190), (93, 91, 175), (82, 46, 120),
+    (48, 20, 55), (110, 35, 75), (165, 70, 75), (200, 125, 110), (220, 180, 175),
+    (226, 217, 226),
+];
+
+fn interpolate(stops: &[(u8, u8, u8)], t: f32) -> u32 {
+    let position = t * (stops.len() - 1) as f32;
+    let i = (position.floor() as usize).min(stops.len() - 2);
+    let fraction = position - i as f32;
+
+    let (r0, g0, b0) = stops[i];
+    let (r1, g1, b1) = stops[i + 1];
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * fraction).round() as u8;
+
+    rgb_to_colour(mix(r0, r1), mix(g0, g1), mix(b0, b1))
+}
+
+impl Ramp {
+    pub const ALL: [Ramp; 4] = [
+        Ramp::Viridis,
+        Ramp::Greyscale,
+        Ramp::Diverging,
+        Ramp::Cyclic,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Ramp::Viridis => "viridis",
+            Ramp::Greyscale => "greyscale",
+            Ramp::Diverging => "diverging",
+            Ramp::Cyclic => "cyclic",
+        }
+    }
+
+    pub fn colour(self, t: f32) -> u32 {
+        let t = if t.is_nan() { 0.0 } else { t };
+
+        match self {
+            Ramp::Viridis => interpolate(&VIRIDIS, t.clamp(0.0, 1.0)),
+            Ramp::Greyscale => {
+                let level = (t.clamp(0.0, 1.0) * 255.0).round() as u8;
+                rgb_to_colour(level, level, level)
+            }
+            Ramp::Diverging => interpolate(&DIVERGING, t.clamp(0.0, 1.0)),
+            Ramp::Cyclic => interpolate(&CYCLIC, t - t.floor()),
+        }
+    }
+}
+
+// Multiplies each channel by `factor`, which is expected to be between 0.0 and 1.0.
+pub fn scale_brightness(colour: u32, factor: f32) -> u32 {
+    let scale = |shift: u32| {
+        ((colour >> shift & 0xFF) as f32 * factor)
+            .round()
+            .clamp(0.0, 255.0) as u8
+    };
+
+    rgb_to_colour(scale(0), scale(8), scale(16))
+}
+
+// Where `value` is between `-scale` and `scale`, with zero in the middle. This is what the
+// diverging ramp is for, but it works with the others too.
+pub fn signed_fraction(value: i32, scale: i32) -> f32 {
+    0.5 + value as f32 / (2 * scale.max(1)) as f32
+}
+
+// Finds the exact colour in the palette if it is there, and the closest one otherwise,
+// since things like the circles blend colours that are not in the palette.
+pub fn nearest_palette_index(palette: &[u32], colour: u32) -> u8 {
+    if let Some(index) = palette.iter().position(|&c| c == colour) {
+        return index as u8;
+    }
+
+    let channels = |c: u32| [c & 0xFF, (c >> 8) & 0xFF, (c >> 16) & 0xFF];
+
+    let [r, g, b] = channels(colour);
+    let mut best_index = 0;
+    let mut best_distance = u32::MAX;
+    for (index, &c) in palette.iter().enumerate() {
+        let [p_r, p_g, p_b] = channels(c);
+        let distance = (r as i32 - p_r as i32).pow(2) as u32
+            + (g as i32 - p_g as i32).pow(2) as u32
+            + (b as i32 - p_b as i32).pow(2) as u32;
+        if distance < best_distance {
+            best_distance = distance;
+            best_index = index;
+        }
+    }
+
+    best_index as u8
+}
+
+// A ramp, optionally snapped to the nearest `PALETTE` colours. Snapping loses detail, but
+// the result is then affected by the screen palette, and exports to GIF exactly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ColourMap {
+    pub ramp: Ramp,
+    pub quantised: bool,
+}
+
+impl ColourMap {
+    pub fn colour(&self, t: f32) -> u32 {
+        self.shaded(t, 1.0)
+    }
+
+    // Like `colour`, but with the brightness scaled by `factor` first, so a quantised map
+    // still only uses the palette.
+    pub fn shaded(&self, t: f32, factor: f32) -> u32 {
+        let colour = scale_brightness(self.ramp.colour(t), factor);
+
+        if self.quantised {
+            PALETTE[nearest_palette_index(&PALETTE, colour) as usize]
+        } else {
+            colour
+        }
+    }
+}
+
+impl Framebuffer {
+    // A bar showing the colour map from left to right, with `low` and `high` written under
+    // its ends. The whole thing is `FONT_SIZE` taller than `height`.
+    pub fn draw_legend(
+        &mut self,
+        colour_map: &ColourMap,
+        (x, y): (u8, u8),
+        (width, height): (u8, u8),
+        (low, high): (&str, &str),
+    ) {
+        let (x, y, width, height) = (x as usize, y as usize, width as usize, height as usize);
+
+        self.draw_rect(x, y, width, height, WHITE);
+        let inner_width = width.saturating_sub(2);
+        for column in 0..inner_width {
+            let t = column as f32 / (inner_width.max(2) - 1) as f32;
+            self.draw_filled_rect(
+                x + 1 + column,
+                y + 1,
+                1,
+                height.saturating_sub(2),
+                colour_map.colour(t),
+            );
+        }
+
+        let label_y = y + height + 1;
+        self.print_line(low.as_bytes(), x, label_y, WHITE_INDEX);
+
+        let high_width = high.len() * FONT_ADVANCE as usize;
+        let high_x = (x + width).saturating_sub(high_width);
+        self.print_line(high.as_bytes(), high_x, label_y, WHITE_INDEX);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brightness(colour: u32) -> u32 {
+        (colour & 0xFF) + (colour >> 8 & 0xFF) + (colour >> 16 & 0xFF)
+    }
+
+    #[test]
+    fn the_sequential_ramps_get_brighter() {
+        for &ramp in &[Ramp::Viridis, Ramp::Greyscale] {
+            let mut previous = 0;
+            for i in 0..=16 {
+                let current = brightness(ramp.colour(i as f32 / 16.0));
+                assert!(current >= previous, "{} got darker", ramp.name());
+                previous = current;
+            }
+        }
+    }
+
+    #[test]
+    fn the_ends_of_the_ramps_are_their_first_and_last_stops() {
+        assert_eq!(Ramp::Viridis.colour(0.0), rgb_to_colour(68, 1, 84));
+        assert_eq!(Ramp::Viridis.colour(1.0), rgb_to_colour(253, 231, 37));
+        assert_eq!(Ramp::Viridis.colour(-3.0), Ramp::Viridis.colour(0.0));
+        assert_eq!(Ramp::Diverging.colour(signed_fraction(0, 8)), 0xFFDD_DDDD);
+        assert_eq!(Ramp::Cyclic.colour(0.25), Ramp::Cyclic.colour(1.25));
+    }
+
+    #[test]
+    fn quantised_maps_only_use_the_palette() {
+        let colour_map = ColourMap {
+            ramp: Ramp::Viridis,
+            quantised: true,
+        };
+
+        for i in 0..=16 {
+            assert!(PALETTE.contains(&colour_map.colour(i as f32 / 16.0)));
+            assert!(PALETTE.contains(&colour_map.shaded(i as f32 / 16.0, 0.5)));
+        }
+    }
+
+    #[test]
+    fn the_legend_shows_both_ends_of_the_map() {
+        let colour_map = ColourMap::default();
+        let mut framebuffer = Framebuffer::new();
+
+        framebuffer.draw_legend(&colour_map, (10, 10), (34, 6), ("0", "9"));
+
+        let at = |x, y| framebuffer.buffer[framebuffer.index(x, y).unwrap()];
+        assert_eq!(at(11, 12), colour_map.colour(0.0));
+        assert_eq!(at(42, 12), colour_map.colour(1.0));
+        assert_eq!(at(10, 10), WHITE);
+    }
+}
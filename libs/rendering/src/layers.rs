@@ -0,0 +1,248 @@
+use crate::rendering::Framebuffer;
+use crate::viewport::{Viewport, MIN_ZOOM};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    // Each channel is added, saturating at full brightness.
+    Additive,
+    // Each channel is scaled by the other, so white leaves what is below unchanged.
+    Multiply,
+    // Each channel is XORed, so drawing the same thing twice undoes it.
+    Xor,
+}
+
+// Combines `colour` with `background` according to `mode`, then mixes that with the
+// background by the colour's alpha scaled by `opacity`. The result is always opaque.
+pub fn blend_pixel(mode: BlendMode, background: u32, colour: u32, opacity: u8) -> u32 {
+    let alpha = (colour >> 24) * opacity as u32 / 255;
+    if alpha == 0 {
+        return background;
+    }
+
+    let mut result = 0xFF00_0000;
+    for shift in &[0, 8, 16] {
+        let b = (background >> shift) & 0xFF;
+        let c = (colour >> shift) & 0xFF;
+
+        let blended = match mode {
+            BlendMode::Normal => c,
+            BlendMode::Additive => (b + c).min(0xFF),
+            BlendMode::Multiply => b * c / 0xFF,
+            BlendMode::Xor => b ^ c,
+        };
+
+        let mixed = (blended * alpha + b * (0xFF - alpha)) / 0xFF;
+        result |= mixed << shift;
+    }
+
+    result
+}
+
+// Anything drawn over the layers below. Pixels with an alpha of zero are left out, which
+// is what a cleared layer is full of.
+pub struct Layer {
+    pub framebuffer: Framebuffer,
+    pub blend_mode: BlendMode,
+    pub opacity: u8,
+    pub visible: bool,
+}
+
+impl Layer {
+    pub fn new(blend_mode: BlendMode) -> Self {
+        let mut framebuffer = Framebuffer::new();
+        framebuffer.clear();
+
+        Layer {
+            framebuffer,
+            blend_mode,
+            opacity: 0xFF,
+            visible: true,
+        }
+    }
+}
+
+// What a frame is built up from, from the bottom up. The plot is kept between frames, so
+// it only needs redrawing when what it shows changes, and it is seen through the viewport
+// when compositing, so zooming and panning don't redraw it either. Everything above it is
+// cleared each frame, so it can be drawn without redrawing the plot below.
+pub struct Layers {
+    pub plot: Framebuffer,
+    // For things drawn on top of the plot that aren't part of it, like the minimap. It is
+    // partly see-through, so it hides less of the plot.
+    pub overlay: Layer,
+    // Text and windows.
+    pub ui: Layer,
+    // XORed, so white shows up over any colour, and is always a different colour from
+    // what it is over.
+    pub cursor: Layer,
+    // The part of the plot in the viewport, scaled up to the size of the grid.
+    zoomed: Framebuffer,
+}
+
+impl Default for Layers {
+    fn default() -> Self {
+        let mut overlay = Layer::new(BlendMode::Normal);
+        overlay.opacity = 0xC0;
+
+        Layers {
+            plot: Framebuffer::new(),
+            overlay,
+            ui: Layer::new(BlendMode::Normal),
+            cursor: Layer::new(BlendMode::Xor),
+            zoomed: Framebuffer::new(),
+        }
+    }
+}
+
+impl Layers {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn clear_above_plot(&mut self) {
+        self.overlay.framebuffer.clear();
+        self.ui.framebuffer.clear();
+        self.cursor.framebuffer.clear();
+    }
+
+    // The layers are all the size of the grid, so if `out` is larger, they end up in its
+    // top left corner and the rest of it is left alone.
+    pub fn composite(&mut self, viewport: Viewport, out: &mut Framebuffer) {
+        let plot = if viewport.zoom > MIN_ZOOM {
+            self.zoomed.draw_zoomed(&self.plot, viewport);
+            &self.zoomed
+        } else {
+            &self.plot
+        };
+
+        let width = plot.width.min(out.width);
+        let height = plot.height.min(out.height);
+
+        for y in 0..height {
+            let row = y * plot.width;
+            let out_row = &mut out.buffer[y * out.width..y * out.width + width];
+            out_row.copy_from_slice(&plot.buffer[row..row + width]);
+
+            for layer in &[&self.overlay, &self.ui, &self.cursor] {
+                if !layer.visible || layer.opacity == 0 {
+                    continue;
+                }
+
+                let colours = &layer.framebuffer.buffer[row..row + width];
+                for (pixel, &colour) in out_row.iter_mut().zip(colours.iter()) {
+                    *pixel = blend_pixel(layer.blend_mode, *pixel, colour, layer.opacity);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BACKGROUND: u32 = 0xFF40_8020;
+
+    #[test]
+    fn each_blend_mode_combines_the_channels() {
+        let colour = 0xFF80_80F0;
+
+        assert_eq!(
+            blend_pixel(BlendMode::Normal, BACKGROUND, colour, 0xFF),
+            colour
+        );
+        assert_eq!(
+            blend_pixel(BlendMode::Additive, BACKGROUND, colour, 0xFF),
+            0xFFC0_FFFF
+        );
+        assert_eq!(
+            blend_pixel(BlendMode::Multiply, BACKGROUND, 0xFFFF_8000, 0xFF),
+            0xFF40_4000
+        );
+        assert_eq!(
+            blend_pixel(BlendMode::Xor, BACKGROUND, colour, 0xFF),
+            0xFFC0_00D0
+        );
+    }
+
+    #[test]
+    fn transparency_and_opacity_both_fade_the_colour() {
+        assert_eq!(
+            blend_pixel(BlendMode::Normal, BACKGROUND, 0x00FF_FFFF, 0xFF),
+            BACKGROUND
+        );
+        assert_eq!(
+            blend_pixel(BlendMode::Normal, BACKGROUND, 0xFFFF_FFFF, 0),
+            BACKGROUND
+        );
+        assert_eq!(
+            blend_pixel(BlendMode::Normal, 0xFF00_0000, 0xFFFF_FFFF, 0x33),
+            0xFF33_3333
+        );
+    }
+
+    #[test]
+    fn cleared_layers_leave_the_plot_as_it_is() {
+        let mut layers = Layers::new();
+        layers.plot.clear_to(BACKGROUND);
+        layers.ui.framebuffer.buffer[3] = 0xFF00_0000;
+        layers.cursor.blend_mode = BlendMode::Xor;
+        layers.cursor.framebuffer.buffer[3] = 0xFFFF_FFFF;
+
+        let mut out = Framebuffer::new();
+        layers.composite(Viewport::default(), &mut out);
+
+        assert_eq!(out.buffer[0], BACKGROUND);
+        assert_eq!(out.buffer[3], 0xFFFF_FFFF);
+    }
+
+    #[test]
+    fn zooming_only_changes_the_composite() {
+        let mut layers = Layers::new();
+        layers.plot.clear_to(BACKGROUND);
+        layers.plot.buffer[0] = 0xFF00_0000;
+        let plot = layers.plot.buffer.clone();
+
+        let mut viewport = Viewport::default();
+        viewport.zoom_in((0, 0));
+        let mut out = Framebuffer::new();
+        layers.composite(viewport, &mut out);
+
+        assert!(layers.plot.buffer == plot);
+        for i in 0..viewport.zoom {
+            assert_eq!(out.buffer[i], 0xFF00_0000);
+        }
+        assert_eq!(out.buffer[viewport.zoom], BACKGROUND);
+    }
+
+    #[test]
+    fn the_cursor_stands_out_and_the_overlay_shows_the_plot_through() {
+        let mut layers = Layers::new();
+        layers.plot.clear_to(BACKGROUND);
+        layers.cursor.framebuffer.buffer[0] = 0xFFFF_FFFF;
+        layers.overlay.framebuffer.buffer[1] = 0xFFFF_FFFF;
+
+        let mut out = Framebuffer::new();
+        layers.composite(Viewport::default(), &mut out);
+
+        assert_eq!(out.buffer[0], 0xFFBF_7FDF);
+        assert_ne!(out.buffer[1], BACKGROUND);
+        assert_ne!(out.buffer[1], 0xFFFF_FFFF);
+    }
+
+    #[test]
+    fn larger_framebuffers_get_the_layers_in_the_top_left() {
+        let mut layers = Layers::new();
+        layers.plot.clear_to(BACKGROUND);
+
+        let mut out = Framebuffer::with_dimensions(512, 300);
+        out.clear_to(0xFF00_0000);
+        layers.composite(Viewport::default(), &mut out);
+
+        assert_eq!(out.buffer[out.index(255, 255).unwrap()], BACKGROUND);
+        assert_eq!(out.buffer[out.index(256, 0).unwrap()], 0xFF00_0000);
+        assert_eq!(out.buffer[out.index(0, 256).unwrap()], 0xFF00_0000);
+    }
+}
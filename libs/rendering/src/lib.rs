@@ -1,5 +1,23 @@
+mod colour_map;
+pub use self::colour_map::*;
+
 mod constants;
 pub use self::constants::*;
 
+mod layers;
+pub use self::layers::*;
+
+mod palette;
+pub use self::palette::*;
+
 mod rendering;
 pub use self::rendering::*;
+
+mod sheet;
+pub use self::sheet::*;
+
+mod sprite;
+pub use self::sprite::*;
+
+mod viewport;
+pub use self::viewport::*;
@@ -0,0 +1,87 @@
+use crate::constants::{PALETTE, PALETTE_LENGTH};
+
+// Alternatives to `PALETTE` that the screen palette can be set to. Each one keeps the
+// colours in the same order, so the same index plays the same part in all of them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PaletteSet {
+    #[default]
+    Default,
+    // From Okabe and Ito's "Color Universal Design", which stays distinguishable with
+    // each of the common kinds of colourblindness.
+    OkabeIto,
+    // Based on the IBM Design Library's colourblind safe palette.
+    Ibm,
+    // For when no colours can be told apart, so only the brightness is left.
+    Greyscale,
+}
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const OKABE_ITO: [u32; PALETTE_LENGTH] = [
+    0xFFB27200, 0xFF739E00, 0xFF005ED5, 0xFF42E4F0,
+    0xFFA779CC, 0xFF808080, 0xFFFFFFFF, 0xFF000000,
+    0xFF009FE6, 0xFFD2B4F0, 0xFFE9B456, 0xFF1E5A96,
+    0xFF643C00, 0xFF415A00, 0xFFC8C8C8, 0xFFA0D2FA,
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const IBM: [u32; PALETTE_LENGTH] = [
+    0xFFFF8F64, 0xFFF05E78, 0xFF7F26DC, 0xFF00B0FF,
+    0xFF78283C, 0xFF808080, 0xFFFFFFFF, 0xFF000000,
+    0xFF0061FE, 0xFFC896F0, 0xFFFFBE96, 0xFF003C82,
+    0xFF6E2814, 0xFF8C323C, 0xFFC8C8C8, 0xFF96DCFF,
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const GREYSCALE: [u32; PALETTE_LENGTH] = [
+    0xFF505050, 0xFF828282, 0xFF3C3C3C, 0xFFDCDCDC,
+    0xFF282828, 0xFF969696, 0xFFFFFFFF, 0xFF000000,
+    0xFFAAAAAA, 0xFFC8C8C8, 0xFFB9B9B9, 0xFF646464,
+    0xFF141414, 0xFF6E6E6E, 0xFFE6E6E6, 0xFFF0F0F0,
+];
+
+impl PaletteSet {
+    pub const ALL: [PaletteSet; 4] = [
+        PaletteSet::Default,
+        PaletteSet::OkabeIto,
+        PaletteSet::Ibm,
+        PaletteSet::Greyscale,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PaletteSet::Default => "default",
+            PaletteSet::OkabeIto => "okabe-ito",
+            PaletteSet::Ibm => "ibm",
+            PaletteSet::Greyscale => "greyscale",
+        }
+    }
+
+    pub fn colours(self) -> [u32; PALETTE_LENGTH] {
+        match self {
+            PaletteSet::Default => PALETTE,
+            PaletteSet::OkabeIto => OKABE_ITO,
+            PaletteSet::Ibm => IBM,
+            PaletteSet::Greyscale => GREYSCALE,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_set_has_distinct_colours() {
+        for &set in PaletteSet::ALL.iter() {
+            let colours = set.colours();
+            for (i, colour) in colours.iter().enumerate() {
+                assert!(
+                    !colours[i + 1..].contains(colour),
+                    "{} repeats {:08x}",
+                    set.name(),
+                    colour
+                );
+            }
+        }
+    }
+}
@@ -1,15 +1,29 @@
 use text::bytes_lines;
 
 use crate::constants::*;
+use crate::sheet::{font, sprite_sheet};
+use crate::sprite::{SpriteFlags, IDENTITY_REMAP};
 use std::cmp::max;
 
+// Usually the size of the grid, `SCREEN_WIDTH` by `SCREEN_HEIGHT`, but the one that gets
+// shown can be larger, to make room for things next to the grid.
+#[derive(Clone)]
 pub struct Framebuffer {
     pub buffer: Vec<u32>,
+    pub width: usize,
+    pub height: usize,
+    // Like PICO-8's draw palette, drawing palette index `i` draws `draw_palette[i]`
+    // instead. This only applies to things drawn by palette index, like sprites and text.
+    pub draw_palette: [u8; PALETTE_LENGTH],
+    // Like PICO-8's screen palette, `apply_screen_palette` replaces `PALETTE[i]` with
+    // `screen_palette[i]` everywhere, including colours that were drawn directly.
+    pub screen_palette: [u32; PALETTE_LENGTH],
 }
 
 impl PartialEq for Framebuffer {
     fn eq(&self, other: &Framebuffer) -> bool {
-        &self.buffer[..] == &other.buffer[..]
+        (self.width, self.height) == (other.width, other.height)
+            && self.buffer[..] == other.buffer[..]
     }
 }
 
@@ -57,8 +71,82 @@ impl Framebuffer {
         Framebuffer::default()
     }
 
-    pub fn xy_to_i(x: usize, y: usize) -> usize {
-        y.saturating_mul(SCREEN_WIDTH).saturating_add(x)
+    pub fn with_dimensions(width: usize, height: usize) -> Framebuffer {
+        let mut buffer = Vec::new();
+        buffer.resize(width * height, PALETTE[0]);
+
+        Framebuffer {
+            buffer,
+            width,
+            height,
+            draw_palette: IDENTITY_REMAP,
+            screen_palette: PALETTE,
+        }
+    }
+
+    pub fn pal(&mut self, from: u8, to: u8) {
+        self.draw_palette[from as usize % PALETTE_LENGTH] = to % PALETTE_LENGTH as u8;
+    }
+
+    pub fn screen_pal(&mut self, index: u8, colour: u32) {
+        self.screen_palette[index as usize % PALETTE_LENGTH] = colour;
+    }
+
+    pub fn reset_pal(&mut self) {
+        self.draw_palette = IDENTITY_REMAP;
+        self.screen_palette = PALETTE;
+    }
+
+    // The colour drawn for palette index `index`, once the draw palette has been applied.
+    pub fn palette_colour(&self, index: u8) -> u32 {
+        PALETTE[self.draw_palette[index as usize % PALETTE_LENGTH] as usize]
+    }
+
+    // This is meant to be done once a frame is otherwise finished, since anything drawn
+    // in one of the `PALETTE` colours afterwards will not be swapped.
+    pub fn apply_screen_palette(&mut self) {
+        if self.screen_palette == PALETTE {
+            return;
+        }
+
+        for pixel in self.buffer.iter_mut() {
+            if let Some(i) = PALETTE.iter().position(|&colour| colour == *pixel) {
+                *pixel = self.screen_palette[i];
+            }
+        }
+    }
+
+    // Doesn't check the position is inside the framebuffer. See `index` for that.
+    pub fn xy_to_i(&self, x: usize, y: usize) -> usize {
+        y.saturating_mul(self.width).saturating_add(x)
+    }
+
+    // For positions that are known to be inside the framebuffer.
+    #[inline]
+    fn set(&mut self, x: usize, y: usize, colour: u32) {
+        let i = y * self.width + x;
+        self.buffer[i] = colour;
+    }
+
+    #[inline]
+    pub fn index(&self, x: usize, y: usize) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    // Copies the whole of `source` over this, with its top left corner at `(x, y)`.
+    // Anything that doesn't fit is left out.
+    pub fn blit(&mut self, source: &Framebuffer, x: usize, y: usize) {
+        for source_y in 0..source.height {
+            for source_x in 0..source.width {
+                if let Some(i) = self.index(x + source_x, y + source_y) {
+                    self.buffer[i] = source.buffer[source_y * source.width + source_x];
+                }
+            }
+        }
     }
 
     pub fn draw_filled_rect(
@@ -74,8 +162,7 @@ impl Framebuffer {
 
         for current_y in y..one_past_bottom_edge {
             for current_x in x..one_past_right_edge {
-                let i = Framebuffer::xy_to_i(current_x, current_y);
-                if i < self.buffer.len() {
+                if let Some(i) = self.index(current_x, current_y) {
                     self.buffer[i] = colour;
                 }
             }
@@ -88,15 +175,13 @@ impl Framebuffer {
 
         for current_y in y..one_past_bottom_edge {
             {
-                let i = Framebuffer::xy_to_i(x, current_y);
-                if i < self.buffer.len() {
+                if let Some(i) = self.index(x, current_y) {
                     self.buffer[i] = colour;
                 }
             }
 
             {
-                let i = Framebuffer::xy_to_i(one_past_right_edge - 1, current_y);
-                if i < self.buffer.len() {
+                if let Some(i) = self.index(one_past_right_edge - 1, current_y) {
                     self.buffer[i] = colour;
                 }
             }
@@ -104,15 +189,13 @@ impl Framebuffer {
 
         for current_x in x..one_past_right_edge {
             {
-                let i = Framebuffer::xy_to_i(current_x, y);
-                if i < self.buffer.len() {
+                if let Some(i) = self.index(current_x, y) {
                     self.buffer[i] = colour;
                 }
             }
 
             {
-                let i = Framebuffer::xy_to_i(current_x, one_past_bottom_edge - 1);
-                if i < self.buffer.len() {
+                if let Some(i) = self.index(current_x, one_past_bottom_edge - 1) {
                     self.buffer[i] = colour;
                 }
             }
@@ -141,22 +224,26 @@ impl Framebuffer {
         let mut y = 0isize;
         let mut err = 2 - 2 * r; /* II. Quadrant */
         while {
-            self.buffer[Framebuffer::xy_to_i(
+            self.set(
                 (x_mid as isize - x) as usize,
                 (y_mid as isize + y) as usize,
-            )] = colour; /*   I. Quadrant */
-            self.buffer[Framebuffer::xy_to_i(
+                colour,
+            ); /*   I. Quadrant */
+            self.set(
                 (x_mid as isize - y) as usize,
                 (y_mid as isize - x) as usize,
-            )] = colour; /*  II. Quadrant */
-            self.buffer[Framebuffer::xy_to_i(
+                colour,
+            ); /*  II. Quadrant */
+            self.set(
                 (x_mid as isize + x) as usize,
                 (y_mid as isize - y) as usize,
-            )] = colour; /* III. Quadrant */
-            self.buffer[Framebuffer::xy_to_i(
+                colour,
+            ); /* III. Quadrant */
+            self.set(
                 (x_mid as isize + y) as usize,
                 (y_mid as isize + x) as usize,
-            )] = colour; /*  IV. Quadrant */
+                colour,
+            ); /*  IV. Quadrant */
             r = err;
             if r <= y {
                 y += 1;
@@ -187,7 +274,9 @@ impl Framebuffer {
 
     #[inline]
     pub fn blend_xy(&mut self, x: usize, y: usize, colour: u32) {
-        self.blend(Framebuffer::xy_to_i(x, y), colour);
+        if let Some(i) = self.index(x, y) {
+            self.blend(i, colour);
+        }
     }
 
     //see http://members.chello.at/easyfilter/bresenham.c
@@ -333,7 +422,7 @@ impl Framebuffer {
                 current_x = (xm - x2 - 1) as usize;
                 current_y = (ym + y) as usize;
                 while current_x > x_mid || current_y > y_mid {
-                    self.buffer[Framebuffer::xy_to_i(current_x, current_y)] = colour;
+                    self.set(current_x, current_y, colour);
 
                     current_x -= 1;
                     current_y -= 1;
@@ -342,7 +431,7 @@ impl Framebuffer {
                 current_x = (xm + y) as usize;
                 current_y = (ym + x2 + 1) as usize;
                 while current_x > x_mid || current_y < y_mid {
-                    self.buffer[Framebuffer::xy_to_i(current_x, current_y)] = colour;
+                    self.set(current_x, current_y, colour);
 
                     current_x -= 1;
                     current_y += 1;
@@ -351,7 +440,7 @@ impl Framebuffer {
                 current_x = (xm - y) as usize;
                 current_y = (ym - x2 - 1) as usize;
                 while current_x < x_mid || current_y > y_mid {
-                    self.buffer[Framebuffer::xy_to_i(current_x, current_y)] = colour;
+                    self.set(current_x, current_y, colour);
 
                     current_x += 1;
                     current_y -= 1;
@@ -360,7 +449,7 @@ impl Framebuffer {
                 current_x = (xm + x2 + 1) as usize;
                 current_y = (ym - y) as usize;
                 while current_x < x_mid || current_y < y_mid {
-                    self.buffer[Framebuffer::xy_to_i(current_x, current_y)] = colour;
+                    self.set(current_x, current_y, colour);
 
                     current_x += 1;
                     current_y += 1;
@@ -373,7 +462,7 @@ impl Framebuffer {
             x < 0
         } {}
 
-        self.buffer[Framebuffer::xy_to_i(x_mid, y_mid)] = colour;
+        self.set(x_mid, y_mid, colour);
     }
 
     pub fn sspr(
@@ -382,40 +471,30 @@ impl Framebuffer {
         sprite_y: u8,
         sprite_w: u8,
         sprite_h: u8,
-        display_x: u8,
-        display_y: u8,
+        display_x: usize,
+        display_y: usize,
     ) {
-        const S_WIDTH: usize = GFX_WIDTH as usize;
-        const D_WIDTH: usize = SCREEN_WIDTH as usize;
-
-        let s_w = sprite_w as usize;
-        let s_h = sprite_h as usize;
-
-        let s_x = sprite_x as usize;
-        let s_y = sprite_y as usize;
+        self.sspr_scaled(
+            (sprite_x, sprite_y, sprite_w, sprite_h),
+            display_x,
+            display_y,
+            1,
+        );
+    }
 
-        let d_x = display_x as usize;
-        let d_y = display_y as usize;
-
-        let d_x_max = d_x + s_w;
-        let d_y_max = d_y + s_h;
-
-        let mut current_s_y = s_y;
-        for y in d_y..d_y_max {
-            let mut current_s_x = s_x;
-            for x in d_x..d_x_max {
-                let colour = GFX[current_s_x + current_s_y * S_WIDTH] as usize;
-                //make purple transparent
-                if colour != 4 {
-                    let index = x + y * D_WIDTH;
-                    if index < self.buffer.len() {
-                        self.buffer[index] = PALETTE[colour];
-                    }
-                }
-                current_s_x += 1;
-            }
-            current_s_y += 1;
-        }
+    // Each pixel of the sprite becomes a `scale` by `scale` square.
+    pub fn sspr_scaled<R: Into<Rect>>(
+        &mut self,
+        sprite_rect: R,
+        display_x: usize,
+        display_y: usize,
+        scale: u8,
+    ) {
+        let flags = SpriteFlags {
+            scale,
+            ..SpriteFlags::default()
+        };
+        self.sspr_with_flags(sprite_rect, display_x, display_y, &flags);
     }
 
     pub fn sspr_flip_both(
@@ -424,84 +503,133 @@ impl Framebuffer {
         sprite_y: u8,
         sprite_w: u8,
         sprite_h: u8,
-        display_x: u8,
-        display_y: u8,
+        display_x: usize,
+        display_y: usize,
     ) {
-        const S_WIDTH: usize = GFX_WIDTH as usize;
-        const D_WIDTH: usize = SCREEN_WIDTH as usize;
-
-        let s_w = sprite_w as usize;
-        let s_h = sprite_h as usize;
-
-        let s_x = sprite_x as usize;
-        let s_y = sprite_y as usize;
+        // Unlike the rest of the sprite drawing, this has always left out red rather than
+        // purple, and the sprites drawn with it rely on that.
+        let flags = SpriteFlags {
+            flip_x: true,
+            flip_y: true,
+            transparent: Some(RED_INDEX),
+            ..SpriteFlags::default()
+        };
+        self.sspr_with_flags(
+            (sprite_x, sprite_y, sprite_w, sprite_h),
+            display_x,
+            display_y,
+            &flags,
+        );
+    }
 
-        let d_x = display_x as usize;
-        let d_y = display_y as usize;
-
-        let d_x_max = d_x + s_w;
-        let d_y_max = d_y + s_h;
-
-        let mut current_s_y = s_y + s_h - 1;
-        for y in d_y..d_y_max {
-            let mut current_s_x = s_x + s_w - 1;
-            for x in d_x..d_x_max {
-                let colour = GFX[current_s_x + current_s_y * S_WIDTH] as usize;
-                //make purple transparent
-                if colour != 2 {
-                    let index = x + y * D_WIDTH;
-                    if index < self.buffer.len() {
-                        self.buffer[index] = PALETTE[colour];
-                    }
+    // Draws the part of the sprite sheet inside `sprite_rect` with its top left corner at
+    // `(display_x, display_y)`. The rect is given before any rotation.
+    pub fn sspr_with_flags<R: Into<Rect>>(
+        &mut self,
+        sprite_rect: R,
+        display_x: usize,
+        display_y: usize,
+        flags: &SpriteFlags,
+    ) {
+        let sheet = sprite_sheet();
+
+        let Rect { x, y, w, h } = sprite_rect.into();
+        let (s_x, s_y) = (x as usize, y as usize);
+        let dimensions = (w as usize, h as usize);
+
+        let scale = flags.scale as usize;
+
+        for y in 0..dimensions.1 {
+            for x in 0..dimensions.0 {
+                let index = sheet.get(s_x + x, s_y + y);
+                if let Some(colour) = flags.colour_index(index) {
+                    let (t_x, t_y) = flags.transform((x, y), dimensions);
+                    self.draw_filled_rect(
+                        display_x + t_x * scale,
+                        display_y + t_y * scale,
+                        scale,
+                        scale,
+                        self.palette_colour(colour),
+                    );
                 }
-                current_s_x -= 1;
             }
-            current_s_y -= 1;
         }
     }
 
-    pub fn spr(&mut self, sprite_number: u8, x: u8, y: u8) {
-        let (sprite_x, sprite_y) = get_sprite_xy(sprite_number);
-        self.sspr(sprite_x, sprite_y, SPRITE_SIZE, SPRITE_SIZE, x, y);
+    pub fn spr(&mut self, sprite_number: u8, x: usize, y: usize) {
+        self.spr_scaled(sprite_number, x, y, 1);
     }
 
-    pub fn spr_flip_both(&mut self, sprite_number: u8, x: u8, y: u8) {
+    pub fn spr_scaled(&mut self, sprite_number: u8, x: usize, y: usize, scale: u8) {
+        let flags = SpriteFlags {
+            scale,
+            ..SpriteFlags::default()
+        };
+        self.spr_with_flags(sprite_number, x, y, &flags);
+    }
+
+    pub fn spr_flip_both(&mut self, sprite_number: u8, x: usize, y: usize) {
         let (sprite_x, sprite_y) = get_sprite_xy(sprite_number);
         self.sspr_flip_both(sprite_x, sprite_y, SPRITE_SIZE, SPRITE_SIZE, x, y);
     }
 
-    pub fn print(&mut self, bytes: &[u8], x: u8, mut y: u8, colour: u8) {
+    pub fn spr_with_flags(&mut self, sprite_number: u8, x: usize, y: usize, flags: &SpriteFlags) {
+        let (sprite_x, sprite_y) = get_sprite_xy(sprite_number);
+        self.sspr_with_flags((sprite_x, sprite_y, SPRITE_SIZE, SPRITE_SIZE), x, y, flags);
+    }
+
+    pub fn print(&mut self, bytes: &[u8], x: usize, mut y: usize, colour: u8) {
         for line in bytes_lines(bytes) {
             self.print_line(line, x, y, colour);
-            y = y.saturating_add(FONT_SIZE);
+            y += FONT_SIZE as usize;
+        }
+    }
+
+    pub fn print_line(&mut self, bytes: &[u8], x: usize, y: usize, colour: u8) {
+        self.print_line_scaled(bytes, x, y, colour, 1);
+    }
+
+    pub fn print_scaled(&mut self, bytes: &[u8], x: usize, mut y: usize, colour: u8, scale: u8) {
+        for line in bytes_lines(bytes) {
+            self.print_line_scaled(line, x, y, colour, scale);
+            y += FONT_SIZE as usize * scale as usize;
         }
     }
 
-    pub fn print_line(&mut self, bytes: &[u8], mut x: u8, y: u8, colour: u8) {
+    pub fn print_line_scaled(
+        &mut self,
+        bytes: &[u8],
+        mut x: usize,
+        y: usize,
+        colour: u8,
+        scale: u8,
+    ) {
         let mut bytes_iter = bytes.iter();
 
         while let Some(&c) = bytes_iter.next() {
             let (sprite_x, sprite_y) = get_char_xy(c);
-            self.print_char_raw(sprite_x, sprite_y, FONT_SIZE, FONT_SIZE, x, y, colour);
-            x = x.saturating_add(FONT_ADVANCE);
+            self.print_char_raw(
+                sprite_x, sprite_y, FONT_SIZE, FONT_SIZE, x, y, colour, scale,
+            );
+            x += FONT_ADVANCE as usize * scale as usize;
         }
     }
 
-    pub fn print_line_raw(&mut self, bytes: &[u8], mut x: u8, y: u8, colour: u8) {
+    pub fn print_line_raw(&mut self, bytes: &[u8], mut x: usize, y: usize, colour: u8) {
         for &c in bytes {
             let (sprite_x, sprite_y) = get_char_xy(c);
-            self.print_char_raw(sprite_x, sprite_y, FONT_SIZE, FONT_SIZE, x, y, colour);
-            x = x.saturating_add(FONT_ADVANCE);
+            self.print_char_raw(sprite_x, sprite_y, FONT_SIZE, FONT_SIZE, x, y, colour, 1);
+            x += FONT_ADVANCE as usize;
         }
     }
 
-    pub fn print_single_line_number(&mut self, number: usize, x: u8, y: u8, colour: u8) {
+    pub fn print_single_line_number(&mut self, number: usize, x: usize, y: usize, colour: u8) {
         self.print_line_raw(number.to_string().as_bytes(), x, y, colour);
     }
 
-    pub fn print_char(&mut self, character: u8, x: u8, y: u8, colour: u8) {
+    pub fn print_char(&mut self, character: u8, x: usize, y: usize, colour: u8) {
         let (sprite_x, sprite_y) = get_char_xy(character);
-        self.print_char_raw(sprite_x, sprite_y, FONT_SIZE, FONT_SIZE, x, y, colour);
+        self.print_char_raw(sprite_x, sprite_y, FONT_SIZE, FONT_SIZE, x, y, colour, 1);
     }
 
     fn print_char_raw(
@@ -510,73 +638,79 @@ impl Framebuffer {
         sprite_y: u8,
         sprite_w: u8,
         sprite_h: u8,
-        display_x: u8,
-        display_y: u8,
+        display_x: usize,
+        display_y: usize,
         colour: u8,
+        scale: u8,
     ) {
-        const S_WIDTH: usize = FONT_WIDTH as usize;
-        const D_WIDTH: usize = SCREEN_WIDTH as usize;
-
-        let s_w = sprite_w as usize;
-        let s_h = sprite_h as usize;
+        let font = font();
 
         let s_x = sprite_x as usize;
         let s_y = sprite_y as usize;
 
-        let d_x = display_x as usize;
-        let d_y = display_y as usize;
-
-        let d_x_max = d_x + s_w;
-        let d_y_max = d_y + s_h;
+        let scale = scale as usize;
 
-        let mut current_s_y = s_y;
-        for y in d_y..d_y_max {
-            let mut current_s_x = s_x;
-            for x in d_x..d_x_max {
-                let foxt_pixel_colour = FONT[current_s_x + current_s_y * S_WIDTH] as usize;
+        for y in 0..sprite_h as usize {
+            for x in 0..sprite_w as usize {
+                let foxt_pixel_colour = font.get(s_x + x, s_y + y);
                 //make black transparent
                 if foxt_pixel_colour != 0 {
-                    let index = x + y * D_WIDTH;
-                    if index < self.buffer.len() {
-                        self.buffer[index] = PALETTE[colour as usize & 15];
-                    }
+                    self.draw_filled_rect(
+                        display_x + x * scale,
+                        display_y + y * scale,
+                        scale,
+                        scale,
+                        self.palette_colour(colour),
+                    );
                 }
-                current_s_x += 1;
             }
-            current_s_y += 1;
         }
     }
 
     pub fn full_window(&mut self) {
-        self.window(0, 0, SCREEN_WIDTH as u8, SCREEN_HEIGHT as u8);
+        self.window(0, 0, SCREEN_WIDTH, SCREEN_HEIGHT);
     }
 
     pub fn center_half_window(&mut self) {
         self.window(
-            SCREEN_WIDTH as u8 / 4,
-            SCREEN_HEIGHT as u8 / 4,
-            SCREEN_WIDTH as u8 / 2,
-            SCREEN_HEIGHT as u8 / 2,
+            SCREEN_WIDTH / 4,
+            SCREEN_HEIGHT / 4,
+            SCREEN_WIDTH / 2,
+            SCREEN_HEIGHT / 2,
         );
     }
 
-    pub fn window(&mut self, x: u8, y: u8, w: u8, h: u8) {
+    pub fn window(&mut self, x: usize, y: usize, w: usize, h: usize) {
         self.nine_slice(WINDOW_TOP_LEFT, x, y, w, h);
     }
 
-    pub fn button(&mut self, x: u8, y: u8, w: u8, h: u8) {
+    pub fn button(&mut self, x: usize, y: usize, w: usize, h: usize) {
         self.nine_slice(BUTTON_TOP_LEFT, x, y, w, h);
     }
 
-    pub fn button_hot(&mut self, x: u8, y: u8, w: u8, h: u8) {
+    pub fn button_hot(&mut self, x: usize, y: usize, w: usize, h: usize) {
         self.nine_slice(BUTTON_HOT_TOP_LEFT, x, y, w, h);
     }
 
-    pub fn button_pressed(&mut self, x: u8, y: u8, w: u8, h: u8) {
+    pub fn button_pressed(&mut self, x: usize, y: usize, w: usize, h: usize) {
         self.nine_slice(BUTTON_PRESSED_TOP_LEFT, x, y, w, h);
     }
 
-    pub fn nine_slice(&mut self, top_left: u8, x: u8, y: u8, w: u8, h: u8) {
+    pub fn nine_slice(&mut self, top_left: u8, x: usize, y: usize, w: usize, h: usize) {
+        self.nine_slice_with_flags(top_left, x, y, w, h, &SpriteFlags::default());
+    }
+
+    // Every piece is drawn with `flags`, so for instance a remap can recolour a window.
+    // Flips and rotations apply to each piece in place, rather than to the whole thing.
+    pub fn nine_slice_with_flags(
+        &mut self,
+        top_left: u8,
+        x: usize,
+        y: usize,
+        w: usize,
+        h: usize,
+        flags: &SpriteFlags,
+    ) {
         let TOP_LEFT: u8 = top_left;
         let TOP: u8 = TOP_LEFT + 1;
         let TOP_RIGHT: u8 = TOP + 1;
@@ -589,35 +723,40 @@ impl Framebuffer {
         let BOTTOM: u8 = MIDDLE + SPRITES_PER_ROW;
         let BOTTOM_RIGHT: u8 = MIDDLE_RIGHT + SPRITES_PER_ROW;
 
-        let after_left_corner = x.saturating_add(SPRITE_SIZE);
-        let before_right_corner = x.saturating_add(w).saturating_sub(SPRITE_SIZE);
+        let after_left_corner = x + SPRITE_SIZE as usize;
+        let before_right_corner = (x + w).saturating_sub(SPRITE_SIZE as usize);
 
-        let below_top_corner = y.saturating_add(SPRITE_SIZE);
-        let above_bottom_corner = y.saturating_add(h).saturating_sub(SPRITE_SIZE);
+        let below_top_corner = y + SPRITE_SIZE as usize;
+        let above_bottom_corner = (y + h).saturating_sub(SPRITE_SIZE as usize);
 
         for fill_y in (below_top_corner..above_bottom_corner).step_by(SPRITE_SIZE as _) {
             for fill_x in (after_left_corner..before_right_corner).step_by(SPRITE_SIZE as _) {
-                self.spr(MIDDLE, fill_x, fill_y);
+                self.spr_with_flags(MIDDLE, fill_x, fill_y, flags);
             }
         }
 
         for fill_x in (after_left_corner..before_right_corner).step_by(SPRITE_SIZE as _) {
-            self.spr(TOP, fill_x, y);
-            self.spr(BOTTOM, fill_x, above_bottom_corner);
+            self.spr_with_flags(TOP, fill_x, y, flags);
+            self.spr_with_flags(BOTTOM, fill_x, above_bottom_corner, flags);
         }
 
         for fill_y in (below_top_corner..above_bottom_corner).step_by(SPRITE_SIZE as _) {
-            self.spr(MIDDLE_LEFT, x, fill_y);
-            self.spr(MIDDLE_RIGHT, before_right_corner, fill_y);
+            self.spr_with_flags(MIDDLE_LEFT, x, fill_y, flags);
+            self.spr_with_flags(MIDDLE_RIGHT, before_right_corner, fill_y, flags);
         }
 
-        self.spr(TOP_LEFT, x, y);
-        self.spr(TOP_RIGHT, before_right_corner, y);
-        self.spr(BOTTOM_LEFT, x, above_bottom_corner);
-        self.spr(BOTTOM_RIGHT, before_right_corner, above_bottom_corner);
+        self.spr_with_flags(TOP_LEFT, x, y, flags);
+        self.spr_with_flags(TOP_RIGHT, before_right_corner, y, flags);
+        self.spr_with_flags(BOTTOM_LEFT, x, above_bottom_corner, flags);
+        self.spr_with_flags(
+            BOTTOM_RIGHT,
+            before_right_corner,
+            above_bottom_corner,
+            flags,
+        );
     }
 
-    pub fn bottom_six_slice(&mut self, top_left: u8, x: u8, y: u8, w: u8, h: u8) {
+    pub fn bottom_six_slice(&mut self, top_left: u8, x: usize, y: usize, w: usize, h: usize) {
         let TOP_LEFT: u8 = top_left;
         let TOP: u8 = TOP_LEFT + 1;
         let TOP_RIGHT: u8 = TOP + 1;
@@ -630,11 +769,11 @@ impl Framebuffer {
         let BOTTOM: u8 = MIDDLE + SPRITES_PER_ROW;
         let BOTTOM_RIGHT: u8 = MIDDLE_RIGHT + SPRITES_PER_ROW;
 
-        let after_left_corner = x.saturating_add(SPRITE_SIZE);
-        let before_right_corner = x.saturating_add(w).saturating_sub(SPRITE_SIZE);
+        let after_left_corner = x + SPRITE_SIZE as usize;
+        let before_right_corner = (x + w).saturating_sub(SPRITE_SIZE as usize);
 
-        let below_top_corner = y.saturating_add(SPRITE_SIZE);
-        let above_bottom_corner = y.saturating_add(h).saturating_sub(SPRITE_SIZE);
+        let below_top_corner = y + SPRITE_SIZE as usize;
+        let above_bottom_corner = (y + h).saturating_sub(SPRITE_SIZE as usize);
 
         for fill_y in (below_top_corner..above_bottom_corner).step_by(SPRITE_SIZE as _) {
             for fill_x in (after_left_corner..before_right_corner).step_by(SPRITE_SIZE as _) {
@@ -658,13 +797,13 @@ impl Framebuffer {
         self.spr(BOTTOM_RIGHT, before_right_corner, above_bottom_corner);
     }
 
-    fn three_slice(&mut self, left_edge: u8, x: u8, y: u8, w: u8) {
+    fn three_slice(&mut self, left_edge: u8, x: usize, y: usize, w: usize) {
         let LEFT: u8 = left_edge;
         let MIDDLE: u8 = LEFT + 1;
         let RIGHT: u8 = MIDDLE + 1;
 
-        let after_left_corner = x.saturating_add(SPRITE_SIZE);
-        let before_right_corner = x.saturating_add(w).saturating_sub(SPRITE_SIZE);
+        let after_left_corner = x + SPRITE_SIZE as usize;
+        let before_right_corner = (x + w).saturating_sub(SPRITE_SIZE as usize);
 
         self.spr(LEFT, x, y);
 
@@ -675,23 +814,23 @@ impl Framebuffer {
         self.spr(RIGHT, before_right_corner, y);
     }
 
-    pub fn row(&mut self, x: u8, y: u8, w: u8) {
+    pub fn row(&mut self, x: usize, y: usize, w: usize) {
         self.three_slice(ROW_LEFT_EDGE, x, y, w);
     }
 
-    pub fn row_hot(&mut self, x: u8, y: u8, w: u8) {
+    pub fn row_hot(&mut self, x: usize, y: usize, w: usize) {
         self.three_slice(ROW_HOT_LEFT_EDGE, x, y, w);
     }
 
-    pub fn row_pressed(&mut self, x: u8, y: u8, w: u8) {
+    pub fn row_pressed(&mut self, x: usize, y: usize, w: usize) {
         self.three_slice(ROW_PRESSED_LEFT_EDGE, x, y, w);
     }
 
-    pub fn row_marker(&mut self, x: u8, y: u8, w: u8) {
+    pub fn row_marker(&mut self, x: usize, y: usize, w: usize) {
         self.three_slice(ROW_MARKER_LEFT_EDGE, x, y, w);
     }
 
-    pub fn checkbox(&mut self, x: u8, y: u8, checked: bool) {
+    pub fn checkbox(&mut self, x: usize, y: usize, checked: bool) {
         self.spr(
             if checked {
                 checkbox::CHECKED
@@ -703,7 +842,7 @@ impl Framebuffer {
         );
     }
 
-    pub fn checkbox_hot(&mut self, x: u8, y: u8, checked: bool) {
+    pub fn checkbox_hot(&mut self, x: usize, y: usize, checked: bool) {
         self.spr(
             if checked {
                 checkbox::HOT_CHECKED
@@ -715,7 +854,7 @@ impl Framebuffer {
         );
     }
 
-    pub fn checkbox_pressed(&mut self, x: u8, y: u8, checked: bool) {
+    pub fn checkbox_pressed(&mut self, x: usize, y: usize, checked: bool) {
         self.spr(
             if checked {
                 checkbox::PRESSED_CHECKED
@@ -744,12 +883,37 @@ pub fn get_char_xy(sprite_number: u8) -> (u8, u8) {
     )
 }
 
+pub fn rgb_to_colour(red: u8, green: u8, blue: u8) -> u32 {
+    colour!(red as u32, green as u32, blue as u32, 0xFF)
+}
+
+// All three arguments are expected to be between 0.0 and 1.0. The hue wraps around.
+pub fn hsv_to_colour(hue: f32, saturation: f32, value: f32) -> u32 {
+    let hue = (hue - hue.floor()) * 6.0;
+    let sector = hue.floor();
+    let fraction = hue - sector;
+
+    let p = value * (1.0 - saturation);
+    let q = value * (1.0 - saturation * fraction);
+    let t = value * (1.0 - saturation * (1.0 - fraction));
+
+    let (r, g, b) = match sector as u8 {
+        0 => (value, t, p),
+        1 => (q, value, p),
+        2 => (p, value, t),
+        3 => (p, q, value),
+        4 => (t, p, value),
+        _ => (value, p, q),
+    };
+
+    let to_byte = |c: f32| (c * 255.0).round().clamp(0.0, 255.0) as u8;
+
+    rgb_to_colour(to_byte(r), to_byte(g), to_byte(b))
+}
+
 impl Default for Framebuffer {
     fn default() -> Self {
-        let mut buffer = Vec::new();
-        buffer.resize(SCREEN_WIDTH * SCREEN_HEIGHT, PALETTE[0]);
-
-        Framebuffer { buffer }
+        Framebuffer::with_dimensions(SCREEN_WIDTH, SCREEN_HEIGHT)
     }
 }
 
@@ -802,6 +966,10 @@ impl From<Rect> for ((u8, u8), (u8, u8)) {
 }
 
 pub fn get_text_dimensions(bytes: &[u8]) -> (u8, u8) {
+    get_text_dimensions_scaled(bytes, 1)
+}
+
+pub fn get_text_dimensions_scaled(bytes: &[u8], scale: u8) -> (u8, u8) {
     let mut width: u8 = 0;
     let mut height: u8 = 0;
     for line in bytes_lines(bytes) {
@@ -809,20 +977,26 @@ pub fn get_text_dimensions(bytes: &[u8]) -> (u8, u8) {
         width = max(width, line.len() as u8);
     }
 
-    width = width.saturating_mul(FONT_ADVANCE);
-    height = height.saturating_mul(FONT_SIZE);
+    width = width.saturating_mul(FONT_ADVANCE).saturating_mul(scale);
+    height = height.saturating_mul(FONT_SIZE).saturating_mul(scale);
 
     (width, height)
 }
 
 pub fn center_line_in_rect<R: Into<Rect>>(text_length: u8, r: R) -> (u8, u8) {
+    center_line_in_rect_scaled(text_length, r, 1)
+}
+
+pub fn center_line_in_rect_scaled<R: Into<Rect>>(text_length: u8, r: R, scale: u8) -> (u8, u8) {
     let Rect { x, y, w, h } = r.into();
     let middle_x = x + (w / 2);
     let middle_y = y + (h / 2);
 
-    let text_x =
-        (middle_x as usize).saturating_sub(text_length as usize * FONT_ADVANCE as usize / 2) as u8;
-    let text_y = (middle_y as usize).saturating_sub(FONT_SIZE as usize / 2) as u8;
+    let advance = FONT_ADVANCE as usize * scale as usize;
+    let size = FONT_SIZE as usize * scale as usize;
+
+    let text_x = (middle_x as usize).saturating_sub(text_length as usize * advance / 2) as u8;
+    let text_y = (middle_y as usize).saturating_sub(size / 2) as u8;
 
     (text_x, text_y)
 }
@@ -841,8 +1015,18 @@ pub fn center_rect_in_rect<R: Into<Rect>>((width, height): (u8, u8), r: R) -> (u
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sprite::Rotation;
     use quickcheck::*;
 
+    #[test]
+    fn hsv_to_colour_matches_the_primaries() {
+        assert_eq!(hsv_to_colour(0.0, 1.0, 1.0), 0xFF0000FF);
+        assert_eq!(hsv_to_colour(1.0 / 3.0, 1.0, 1.0), 0xFF00FF00);
+        assert_eq!(hsv_to_colour(2.0 / 3.0, 1.0, 1.0), 0xFFFF0000);
+        assert_eq!(hsv_to_colour(1.0, 1.0, 1.0), 0xFF0000FF);
+        assert_eq!(hsv_to_colour(0.5, 0.0, 0.0), 0xFF000000);
+    }
+
     #[test]
     fn test_get_text_dimensions_then_center_rect_in_rect_matches_center_line_in_rect_for_a_single_line(
     ) {
@@ -870,6 +1054,119 @@ mod tests {
         TestResult::from_bool(text_point == line_point)
     }
 
+    quickcheck! {
+        fn scaled_text_is_centred_the_same_way_as_a_rect(
+            char_count: u8,
+            scale: u8,
+            r: (u8, u8, u8, u8)
+        ) -> TestResult {
+            let scale = scale % 3 + 1;
+            if char_count as usize * FONT_ADVANCE as usize * scale as usize > 255 {
+                return TestResult::discard();
+            }
+
+            let text = vec![b'A'; char_count as usize];
+            let dimensions = get_text_dimensions_scaled(&text, scale);
+
+            TestResult::from_bool(
+                center_rect_in_rect(dimensions, r)
+                    == center_line_in_rect_scaled(char_count, r, scale)
+            )
+        }
+    }
+
+    #[test]
+    fn flipping_a_sprite_both_ways_matches_turning_it_half_way() {
+        let mut flipped = Framebuffer::new();
+        flipped.spr_flip_both(WINDOW_TOP_LEFT, 3, 5);
+
+        let mut turned = Framebuffer::new();
+        let flags = SpriteFlags {
+            rotation: Rotation::Half,
+            transparent: Some(RED_INDEX),
+            ..SpriteFlags::default()
+        };
+        turned.spr_with_flags(WINDOW_TOP_LEFT, 3, 5, &flags);
+
+        assert!(flipped == turned);
+        assert!(flipped != Framebuffer::new());
+    }
+
+    #[test]
+    fn every_colour_index_that_text_allows_can_be_drawn() {
+        let mut framebuffer = Framebuffer::new();
+        for colour in 0..16 {
+            framebuffer.print_char(b'A', (colour * FONT_ADVANCE) as usize, 0, colour);
+        }
+    }
+
+    #[test]
+    fn the_draw_palette_changes_what_gets_drawn_and_the_screen_palette_what_gets_shown() {
+        let mut framebuffer = Framebuffer::new();
+        framebuffer.clear_to(BLUE);
+        framebuffer.pal(WHITE_INDEX, RED_INDEX);
+        framebuffer.print_char(b'A', 0, 0, WHITE_INDEX);
+
+        assert!(framebuffer.buffer.contains(&RED));
+        assert!(!framebuffer.buffer.contains(&WHITE));
+
+        framebuffer.screen_pal(BLUE_INDEX, 0xFF00_0000);
+        framebuffer.apply_screen_palette();
+
+        assert!(framebuffer.buffer.contains(&RED));
+        assert!(!framebuffer.buffer.contains(&BLUE));
+        assert_eq!(
+            framebuffer.buffer[framebuffer.buffer.len() - 1],
+            0xFF00_0000
+        );
+    }
+
+    #[test]
+    fn scaled_text_covers_each_font_pixel_with_a_square() {
+        let colour = PALETTE[WHITE_INDEX as usize];
+        let count = |framebuffer: &Framebuffer| {
+            framebuffer
+                .buffer
+                .iter()
+                .filter(|&&pixel| pixel == colour)
+                .count()
+        };
+
+        let mut small = Framebuffer::new();
+        small.print_line(b"Hi", 0, 0, WHITE_INDEX);
+        let mut large = Framebuffer::new();
+        large.print_line_scaled(b"Hi", 0, 0, WHITE_INDEX, 3);
+
+        assert!(count(&small) > 0);
+        assert_eq!(count(&large), count(&small) * 9);
+        assert_eq!(
+            get_text_dimensions_scaled(b"Hi", 3),
+            (6 * FONT_ADVANCE, 3 * FONT_SIZE)
+        );
+    }
+
+    #[test]
+    fn text_can_go_anywhere_on_a_framebuffer_larger_than_the_grid() {
+        let colour = PALETTE[WHITE_INDEX as usize];
+        let mut framebuffer = Framebuffer::with_dimensions(600, 400);
+        framebuffer.print_line(b"Hi", 500, 300, WHITE_INDEX);
+
+        let (first, last) = (
+            framebuffer.buffer.iter().position(|&pixel| pixel == colour),
+            framebuffer
+                .buffer
+                .iter()
+                .rposition(|&pixel| pixel == colour),
+        );
+        let (first, last) = (first.unwrap(), last.unwrap());
+
+        assert_eq!(framebuffer.xy_to_i(500, 300), 300 * 600 + 500);
+        assert!(first >= framebuffer.xy_to_i(500, 300));
+        assert!(
+            last < framebuffer.xy_to_i(500 + 2 * FONT_ADVANCE as usize, 300 + FONT_SIZE as usize)
+        );
+    }
+
     #[test]
     fn test_center_rect_in_rect_actually_centers_when_possible() {
         quickcheck(
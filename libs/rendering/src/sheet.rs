@@ -0,0 +1,216 @@
+use crate::constants::{FONT_HEIGHT, FONT_WIDTH, GFX_HEIGHT, GFX_WIDTH, PALETTE};
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+
+// Returns the pixels along with the width and height, in the same `0xAABBGGRR` layout as
+// the framebuffer.
+pub fn decode_png(bytes: &[u8]) -> Result<(Vec<u32>, u32, u32), Box<dyn Error>> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+
+    let to_colour = |r: u8, g: u8, b: u8, a: u8| u32::from_le_bytes([r, g, b, a]);
+
+    let data = &buffer[..info.buffer_size()];
+    let pixels = match info.color_type {
+        png::ColorType::Grayscale => data.iter().map(|&l| to_colour(l, l, l, 0xFF)).collect(),
+        png::ColorType::GrayscaleAlpha => data
+            .chunks(2)
+            .map(|c| to_colour(c[0], c[0], c[0], c[1]))
+            .collect(),
+        png::ColorType::Rgb => data
+            .chunks(3)
+            .map(|c| to_colour(c[0], c[1], c[2], 0xFF))
+            .collect(),
+        png::ColorType::Rgba => data
+            .chunks(4)
+            .map(|c| to_colour(c[0], c[1], c[2], c[3]))
+            .collect(),
+        png::ColorType::Indexed => {
+            return Err(format!("Unexpected {:?} PNG after expansion", info.color_type).into());
+        }
+    };
+
+    Ok((pixels, info.width, info.height))
+}
+
+// The font only says whether each pixel is drawn, since the colour is picked when printing.
+pub const FONT_COLOURS: [u32; 2] = [0xFF00_0000, 0xFFFF_FFFF];
+
+// An image where each pixel is an index into a list of colours, like `PALETTE` for the
+// sprite sheet, or `FONT_COLOURS` for the font.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sheet {
+    pub indices: Vec<u8>,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Sheet {
+    // Every pixel has to be exactly one of `colours`, so a stray colour from an image
+    // editor is reported rather than silently drawn as something else. Alpha is ignored.
+    pub fn from_png(
+        png_bytes: &[u8],
+        (width, height): (usize, usize),
+        colours: &[u32],
+    ) -> Result<Self, Box<dyn Error>> {
+        let (pixels, png_width, png_height) = decode_png(png_bytes)?;
+
+        if (png_width as usize, png_height as usize) != (width, height) {
+            return Err(format!(
+                "Expected a {}x{} image, not {}x{}",
+                width, height, png_width, png_height
+            )
+            .into());
+        }
+
+        let rgb = |colour: u32| colour & 0x00FF_FFFF;
+
+        let mut indices = Vec::with_capacity(pixels.len());
+        for (i, &colour) in pixels.iter().enumerate() {
+            match colours.iter().position(|&c| rgb(c) == rgb(colour)) {
+                Some(index) => indices.push(index as u8),
+                None => {
+                    let [r, g, b, _a] = colour.to_le_bytes();
+                    return Err(format!(
+                        "The colour #{:02x}{:02x}{:02x} at ({}, {}) is not in the palette",
+                        r,
+                        g,
+                        b,
+                        i % width,
+                        i / width
+                    )
+                    .into());
+                }
+            }
+        }
+
+        Ok(Sheet {
+            indices,
+            width,
+            height,
+        })
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.indices[x + y * self.width]
+    }
+}
+
+// These are embedded so there is always something to draw with, and can be replaced at
+// runtime, say with art dropped onto the page, without regenerating any Rust source.
+thread_local! {
+    static SPRITE_SHEET: RefCell<Rc<Sheet>> = RefCell::new(Rc::new(
+        Sheet::from_png(
+            include_bytes!("../assets/gfx.png"),
+            (GFX_WIDTH, GFX_HEIGHT),
+            &PALETTE,
+        )
+        .expect("The embedded sprite sheet should be valid"),
+    ));
+
+    static FONT: RefCell<Rc<Sheet>> = RefCell::new(Rc::new(
+        Sheet::from_png(
+            include_bytes!("../assets/font.png"),
+            (FONT_WIDTH, FONT_HEIGHT),
+            &FONT_COLOURS,
+        )
+        .expect("The embedded font should be valid"),
+    ));
+}
+
+pub fn sprite_sheet() -> Rc<Sheet> {
+    SPRITE_SHEET.with(|sheet| sheet.borrow().clone())
+}
+
+pub fn font() -> Rc<Sheet> {
+    FONT.with(|sheet| sheet.borrow().clone())
+}
+
+// If the PNG is invalid, the current sprite sheet is kept.
+pub fn load_sprite_sheet(png_bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    let sheet = Sheet::from_png(png_bytes, (GFX_WIDTH, GFX_HEIGHT), &PALETTE)?;
+
+    SPRITE_SHEET.with(|current| *current.borrow_mut() = Rc::new(sheet));
+
+    Ok(())
+}
+
+// If the PNG is invalid, the current font is kept.
+pub fn load_font(png_bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    let sheet = Sheet::from_png(png_bytes, (FONT_WIDTH, FONT_HEIGHT), &FONT_COLOURS)?;
+
+    FONT.with(|current| *current.borrow_mut() = Rc::new(sheet));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(pixels: &[u32], width: u32, height: u32) -> Vec<u8> {
+        let mut output = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut output, width, height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+
+            let bytes: Vec<u8> = pixels
+                .iter()
+                .flat_map(|c| c.to_le_bytes().to_vec())
+                .collect();
+            writer.write_image_data(&bytes).unwrap();
+        }
+        output
+    }
+
+    #[test]
+    fn the_embedded_sheets_only_use_their_colours() {
+        let sheet = sprite_sheet();
+        assert_eq!(sheet.indices.len(), GFX_WIDTH * GFX_HEIGHT);
+        assert!(sheet.indices.iter().all(|&i| (i as usize) < PALETTE.len()));
+
+        let font = font();
+        assert_eq!(font.indices.len(), FONT_WIDTH * FONT_HEIGHT);
+        assert!(font.indices.contains(&1));
+    }
+
+    #[test]
+    fn pixels_become_their_index_in_the_colours() {
+        let pixels = [
+            PALETTE[3], PALETTE[0], PALETTE[7], PALETTE[3], PALETTE[1], PALETTE[2],
+        ];
+
+        let sheet = Sheet::from_png(&encode(&pixels, 3, 2), (3, 2), &PALETTE).unwrap();
+
+        assert_eq!(sheet.indices, vec![3, 0, 7, 3, 1, 2]);
+        assert_eq!(sheet.get(1, 1), 1);
+    }
+
+    #[test]
+    fn the_wrong_size_or_colours_are_rejected() {
+        let pixels = [PALETTE[0]; 6];
+        assert!(Sheet::from_png(&encode(&pixels, 3, 2), (2, 3), &PALETTE).is_err());
+
+        let mut pixels = pixels;
+        pixels[4] = 0xFF12_3456;
+        let error = Sheet::from_png(&encode(&pixels, 3, 2), (3, 2), &PALETTE).unwrap_err();
+        assert!(error.to_string().contains("#563412 at (1, 1)"));
+    }
+
+    #[test]
+    fn invalid_sheets_leave_the_current_one_in_place() {
+        let before = sprite_sheet();
+
+        assert!(load_sprite_sheet(&encode(&[PALETTE[0]; 4], 2, 2)).is_err());
+
+        assert_eq!(*sprite_sheet(), *before);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::constants::{PALETTE_LENGTH, PURPLE_INDEX};
+
+// Clockwise, applied after any flipping.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    None,
+    Quarter,
+    Half,
+    ThreeQuarters,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpriteFlags {
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub rotation: Rotation,
+    // The palette index in the sprite sheet that gets left out, before any remapping.
+    pub transparent: Option<u8>,
+    // Each palette index in the sprite sheet is drawn as the one at that position here.
+    pub remap: [u8; PALETTE_LENGTH],
+    pub scale: u8,
+}
+
+pub const IDENTITY_REMAP: [u8; PALETTE_LENGTH] = {
+    let mut remap = [0; PALETTE_LENGTH];
+    let mut i = 0;
+    while i < PALETTE_LENGTH {
+        remap[i] = i as u8;
+        i += 1;
+    }
+    remap
+};
+
+impl Default for SpriteFlags {
+    fn default() -> Self {
+        SpriteFlags {
+            flip_x: false,
+            flip_y: false,
+            rotation: Rotation::None,
+            transparent: Some(PURPLE_INDEX),
+            remap: IDENTITY_REMAP,
+            scale: 1,
+        }
+    }
+}
+
+impl SpriteFlags {
+    // The width and height of a `w` by `h` sprite once it has been drawn, before scaling.
+    pub fn dimensions(&self, (w, h): (usize, usize)) -> (usize, usize) {
+        match self.rotation {
+            Rotation::None | Rotation::Half => (w, h),
+            Rotation::Quarter | Rotation::ThreeQuarters => (h, w),
+        }
+    }
+
+    // Where the pixel at `(x, y)` in a `w` by `h` sprite ends up, relative to the top
+    // left corner of where it is drawn, before scaling.
+    pub fn transform(&self, (x, y): (usize, usize), (w, h): (usize, usize)) -> (usize, usize) {
+        let x = if self.flip_x { w - 1 - x } else { x };
+        let y = if self.flip_y { h - 1 - y } else { y };
+
+        match self.rotation {
+            Rotation::None => (x, y),
+            Rotation::Quarter => (h - 1 - y, x),
+            Rotation::Half => (w - 1 - x, h - 1 - y),
+            Rotation::ThreeQuarters => (y, w - 1 - x),
+        }
+    }
+
+    // The palette index to draw for `index` in the sprite sheet, if any.
+    pub fn colour_index(&self, index: u8) -> Option<u8> {
+        if self.transparent == Some(index) {
+            None
+        } else {
+            Some(self.remap[index as usize % PALETTE_LENGTH])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIMENSIONS: (usize, usize) = (3, 2);
+
+    fn flags(flip_x: bool, flip_y: bool, rotation: Rotation) -> SpriteFlags {
+        SpriteFlags {
+            flip_x,
+            flip_y,
+            rotation,
+            ..SpriteFlags::default()
+        }
+    }
+
+    #[test]
+    fn rotations_turn_the_sprite_clockwise() {
+        let top_right = (2, 0);
+
+        let corner = |rotation| flags(false, false, rotation).transform(top_right, DIMENSIONS);
+
+        assert_eq!(corner(Rotation::None), (2, 0));
+        assert_eq!(corner(Rotation::Quarter), (1, 2));
+        assert_eq!(corner(Rotation::Half), (0, 1));
+        assert_eq!(corner(Rotation::ThreeQuarters), (0, 0));
+        assert_eq!(
+            flags(false, false, Rotation::Quarter).dimensions(DIMENSIONS),
+            (2, 3)
+        );
+    }
+
+    #[test]
+    fn flipping_both_ways_is_the_same_as_half_a_turn() {
+        for y in 0..DIMENSIONS.1 {
+            for x in 0..DIMENSIONS.0 {
+                assert_eq!(
+                    flags(true, true, Rotation::None).transform((x, y), DIMENSIONS),
+                    flags(false, false, Rotation::Half).transform((x, y), DIMENSIONS)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn transparency_is_checked_before_remapping() {
+        let mut flags = SpriteFlags {
+            transparent: Some(2),
+            ..SpriteFlags::default()
+        };
+        flags.remap[1] = 2;
+        flags.remap[2] = 1;
+
+        assert_eq!(flags.colour_index(1), Some(2));
+        assert_eq!(flags.colour_index(2), None);
+        assert_eq!(flags.colour_index(3), Some(3));
+    }
+}
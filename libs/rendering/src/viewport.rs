@@ -0,0 +1,217 @@
+use crate::constants::*;
+use crate::rendering::Framebuffer;
+
+pub const MIN_ZOOM: usize = 1;
+pub const MAX_ZOOM: usize = 16;
+
+// The visible part of the 256 by 256 grid. The zoom is kept to a power of two so
+// the source rect always divides the screen evenly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: usize,
+    pub y: usize,
+    pub zoom: usize,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Viewport {
+            x: 0,
+            y: 0,
+            zoom: MIN_ZOOM,
+        }
+    }
+}
+
+impl Viewport {
+    #[inline]
+    pub fn source_width(&self) -> usize {
+        SCREEN_WIDTH / self.zoom
+    }
+
+    #[inline]
+    pub fn source_height(&self) -> usize {
+        SCREEN_HEIGHT / self.zoom
+    }
+
+    pub fn source_rect(&self) -> (usize, usize, usize, usize) {
+        (self.x, self.y, self.source_width(), self.source_height())
+    }
+
+    pub fn contains(&self, (x, y): (usize, usize)) -> bool {
+        x >= self.x
+            && y >= self.y
+            && x < self.x + self.source_width()
+            && y < self.y + self.source_height()
+    }
+
+    pub fn source_to_screen(&self, (x, y): (usize, usize)) -> (usize, usize) {
+        (
+            x.saturating_sub(self.x) * self.zoom,
+            y.saturating_sub(self.y) * self.zoom,
+        )
+    }
+
+    pub fn screen_to_source(&self, (x, y): (usize, usize)) -> (usize, usize) {
+        (self.x + x / self.zoom, self.y + y / self.zoom)
+    }
+
+    // Changes the zoom while keeping the cell under `centre` at the same place on screen.
+    pub fn zoom_around(&mut self, zoom: usize, centre: (usize, usize)) {
+        let zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM).next_power_of_two();
+        if zoom == self.zoom {
+            return;
+        }
+
+        let (screen_x, screen_y) = self.source_to_screen(centre);
+
+        self.zoom = zoom;
+        self.x = centre.0.saturating_sub(screen_x / zoom);
+        self.y = centre.1.saturating_sub(screen_y / zoom);
+
+        self.clamp();
+    }
+
+    pub fn zoom_in(&mut self, centre: (usize, usize)) {
+        let zoom = self.zoom * 2;
+        self.zoom_around(zoom, centre);
+    }
+
+    pub fn zoom_out(&mut self, centre: (usize, usize)) {
+        let zoom = self.zoom / 2;
+        self.zoom_around(zoom, centre);
+    }
+
+    pub fn pan(&mut self, dx: isize, dy: isize) {
+        self.x = (self.x as isize + dx).max(0) as usize;
+        self.y = (self.y as isize + dy).max(0) as usize;
+
+        self.clamp();
+    }
+
+    // Pans the least amount needed for the given cell to be visible.
+    pub fn scroll_to(&mut self, (x, y): (usize, usize)) {
+        let (w, h) = (self.source_width(), self.source_height());
+
+        if x < self.x {
+            self.x = x;
+        } else if x >= self.x + w {
+            self.x = x + 1 - w;
+        }
+
+        if y < self.y {
+            self.y = y;
+        } else if y >= self.y + h {
+            self.y = y + 1 - h;
+        }
+
+        self.clamp();
+    }
+
+    fn clamp(&mut self) {
+        self.x = self.x.min(SCREEN_WIDTH - self.source_width());
+        self.y = self.y.min(SCREEN_HEIGHT - self.source_height());
+    }
+}
+
+impl Framebuffer {
+    // `source` is expected to be at least the size of the grid, and is usually the plot.
+    pub fn draw_zoomed(&mut self, source: &Framebuffer, viewport: Viewport) {
+        let zoom = viewport.zoom;
+        for y in 0..SCREEN_HEIGHT {
+            let source_y = viewport.y + y / zoom;
+            for x in 0..SCREEN_WIDTH {
+                let source_x = viewport.x + x / zoom;
+
+                if let Some(i) = self.index(x, y) {
+                    self.buffer[i] = source.buffer[source.xy_to_i(source_x, source_y)];
+                }
+            }
+        }
+    }
+
+    // Draws a `size` by `size` scaled down copy of `source` with the visible region marked.
+    pub fn draw_minimap(
+        &mut self,
+        source: &Framebuffer,
+        viewport: Viewport,
+        x: usize,
+        y: usize,
+        size: usize,
+    ) {
+        let scale = (SCREEN_WIDTH / size).max(1);
+
+        for current_y in 0..size {
+            for current_x in 0..size {
+                if let Some(i) = self.index(x + current_x, y + current_y) {
+                    self.buffer[i] =
+                        source.buffer[source.xy_to_i(current_x * scale, current_y * scale)];
+                }
+            }
+        }
+
+        self.draw_rect(
+            x.saturating_sub(1),
+            y.saturating_sub(1),
+            size + 2,
+            size + 2,
+            BLACK,
+        );
+
+        let (s_x, s_y, s_w, s_h) = viewport.source_rect();
+        self.draw_rect(
+            x + s_x / scale,
+            y + s_y / scale,
+            (s_w / scale).max(1),
+            (s_h / scale).max(1),
+            YELLOW,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zooming_around_a_cell_keeps_it_in_place_on_screen() {
+        let mut viewport = Viewport::default();
+        let centre = (200, 37);
+
+        let before = viewport.source_to_screen(centre);
+        viewport.zoom_in(centre);
+
+        assert_eq!(viewport.zoom, 2);
+        assert!(viewport.contains(centre));
+        let after = viewport.source_to_screen(centre);
+        assert!(before.0.max(after.0) - before.0.min(after.0) < viewport.zoom);
+        assert!(before.1.max(after.1) - before.1.min(after.1) < viewport.zoom);
+    }
+
+    #[test]
+    fn zoom_is_clamped() {
+        let mut viewport = Viewport::default();
+        for _ in 0..8 {
+            viewport.zoom_in((0, 0));
+        }
+        assert_eq!(viewport.zoom, MAX_ZOOM);
+
+        for _ in 0..8 {
+            viewport.zoom_out((0, 0));
+        }
+        assert_eq!(viewport, Viewport::default());
+    }
+
+    #[test]
+    fn scroll_to_stays_on_the_grid() {
+        let mut viewport = Viewport {
+            x: 0,
+            y: 0,
+            zoom: 4,
+        };
+
+        viewport.scroll_to((255, 255));
+        assert!(viewport.contains((255, 255)));
+        assert_eq!(viewport.x + viewport.source_width(), SCREEN_WIDTH);
+    }
+}
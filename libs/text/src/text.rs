@@ -2,6 +2,11 @@ macro_rules! test_log {
     ($($arg:tt)*) => {};
 }
 
+#[cfg(test)]
+macro_rules! test_println {
+    ($($arg:tt)*) => { println!($($arg)*) };
+}
+
 #[macro_export]
 macro_rules! bytes_concat {
     ($($byte_strings:expr),*$(,)*) => {{
@@ -200,12 +205,18 @@ pub fn slice_until_first_0<'a>(bytes: &'a [u8]) -> &'a [u8] {
 }
 
 // NOTE This does not use a general purpose definition of whitespace.
-// This should count a byte as whitespace iff it has all blank
-// pixels in this game's font.
+// This counts a byte as whitespace if it has all blank pixels in this
+// game's font, which is the space and the control characters below 26,
+// except for 1 and 10. Those two have pixels, but are counted as well:
+// 10 because it is the newline, and 1 because it always has been.
+// From 26 up the control characters are drawn as symbols.
 #[inline]
 pub fn is_byte_whitespace(byte: u8) -> bool {
     let lower_half_byte = byte & 0b0111_1111;
-    lower_half_byte < b' '
+    match lower_half_byte {
+        b'\n' | 1 | b' ' => true,
+        _ => lower_half_byte < 26,
+    }
 }
 
 //See NOTE above.
@@ -218,7 +229,6 @@ pub fn bytes_split_whitespace<'a>(bytes: &'a [u8]) -> impl Iterator<Item = &'a [
 #[cfg(test)]
 mod tests {
     use super::*;
-    use inner_common::test_println;
     use quickcheck::*;
 
     #[test]
@@ -325,6 +335,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn bytes_reflow_breaks_lines_at_spaces() {
+        assert!(is_byte_whitespace(b' '));
+        assert!(is_byte_whitespace(b'\n'));
+        assert!(!is_byte_whitespace(26));
+        assert_eq!(bytes_reflow(b"x0 + x1 + x0x1", 7), b"x0 + x1\n+ x0x1");
+    }
+
     #[test]
     fn reflow_does_not_add_a_space_if_there_is_no_room() {
         assert_eq!(reflow("12345 67890", 5), "12345\n67890".to_string());
@@ -12,9 +12,10 @@ use std::rc::Rc;
 use stdweb::web::event::{IEvent, IKeyboardEvent, KeyDownEvent, KeyUpEvent, KeyboardLocation};
 use stdweb::web::{self, Element, IElement, IEventTarget, INode, INonElementParentNode};
 
+use stdweb::web::TypedArray;
 use stdweb::{UnsafeTypedArray, Value};
 
-use platform_types::{Button, State, StateParams, SFX};
+use platform_types::{Button, Command, Export, Pointer, State, StateParams, SFX};
 
 macro_rules! enclose {
     ( [$( $x:ident ),*] $y:expr ) => {
@@ -27,7 +28,7 @@ macro_rules! enclose {
 
 // This creates a really basic WebGL context for blitting a single texture.
 // On some web browsers this is faster than using a 2d canvas.
-fn setup_webgl(canvas: &Element) -> Value {
+fn setup_webgl(canvas: &Element, (width, height): (usize, usize)) -> Value {
     const FRAGMENT_SHADER: &'static str = r#"
         precision mediump float;
         varying vec2 v_texcoord;
@@ -122,7 +123,10 @@ fn setup_webgl(canvas: &Element) -> Value {
         var sampler_uniform = gl.getUniformLocation( program, "u_sampler" );
         gl.uniform1i( sampler_uniform, 0 );
 
-        var matrix = @{ortho( 0.0, 256.0, 256.0, 0.0 )};
+        var width = @{width as u32};
+        var height = @{height as u32};
+
+        var matrix = @{ortho( 0.0, width as f64, height as f64, 0.0 )};
         var matrix_uniform = gl.getUniformLocation( program, "u_matrix" );
         gl.uniformMatrix4fv( matrix_uniform, false, matrix );
 
@@ -132,12 +136,12 @@ fn setup_webgl(canvas: &Element) -> Value {
             gl.TEXTURE_2D,
             0,
             gl.RGBA,
-            256,
-            256,
+            width,
+            height,
             0,
             gl.RGBA,
             gl.UNSIGNED_BYTE,
-            new Uint8Array( 256 * 256 * 4 )
+            new Uint8Array( width * height * 4 )
           );
         gl.texParameteri( gl.TEXTURE_2D, gl.TEXTURE_MAG_FILTER, gl.NEAREST );
         gl.texParameteri( gl.TEXTURE_2D, gl.TEXTURE_MIN_FILTER, gl.NEAREST );
@@ -146,9 +150,9 @@ fn setup_webgl(canvas: &Element) -> Value {
         gl.bindBuffer( gl.ARRAY_BUFFER, vertex_buffer );
         var vertices = [
             0.0, 0.0,
-            0.0, 256.0,
-            256.0, 0.0,
-            256.0, 256.0
+            0.0, height,
+            width, 0.0,
+            width, height
         ];
         gl.bufferData( gl.ARRAY_BUFFER, new Float32Array( vertices ), gl.STATIC_DRAW );
         gl.vertexAttribPointer( vertex_attr, 2, gl.FLOAT, false, 0, 0 );
@@ -157,9 +161,9 @@ fn setup_webgl(canvas: &Element) -> Value {
         gl.bindBuffer( gl.ARRAY_BUFFER, texcoord_buffer );
         var texcoords = [
             0.0, 0.0,
-            0.0, 256.0 / 256.0,
+            0.0, 1.0,
             1.0, 0.0,
-            1.0, 256.0 / 256.0
+            1.0, 1.0
         ];
         gl.bufferData( gl.ARRAY_BUFFER, new Float32Array( texcoords ), gl.STATIC_DRAW );
         gl.vertexAttribPointer( texcoord_attr, 2, gl.FLOAT, false, 0, 0 );
@@ -174,32 +178,61 @@ fn setup_webgl(canvas: &Element) -> Value {
 
         gl.clearColor( 0.0, 0.0, 0.0, 1.0 );
         gl.enable( gl.DEPTH_TEST );
-        gl.viewport( 0, 0, 256, 256 );
+        gl.viewport( 0, 0, width, height );
 
         return gl;
     )
 }
 
 fn handle_sound(request: SFX) {
-    let request_string = request.to_sound_key();
+    let samples = audio::render_sfx(&request, audio::DEFAULT_SAMPLE_RATE);
 
     js! {
-        if (soundHandler) {
-            soundHandler(@{request_string});
+        if( window.playSamples ) {
+            playSamples( @{TypedArray::from( &samples[..] )}, @{audio::DEFAULT_SAMPLE_RATE} );
         }
     };
 }
 
+fn download(bytes: &[u8], filename: &str, mime_type: &str) {
+    js! {
+        var bytes = @{unsafe { UnsafeTypedArray::new( bytes ) }};
+        var blob = new Blob( [bytes], { type: @{mime_type} } );
+        var url = URL.createObjectURL( blob );
+
+        var link = document.createElement( "a" );
+        link.href = url;
+        link.download = @{filename};
+        document.body.appendChild( link );
+        link.click();
+        document.body.removeChild( link );
+
+        URL.revokeObjectURL( url );
+    }
+}
+
+fn save_export(result: Result<Export, Box<dyn Error>>) {
+    match result {
+        Ok(export) => download(&export.bytes, export.file_name, export.mime_type),
+        Err(error) => {
+            js!( console.error( "Export error:", @{format!( "{}", error )} ); );
+        }
+    }
+}
+
 struct PinkyWeb<S: State> {
     paused: bool,
     busy: bool,
     js_ctx: Value,
     state: S,
+    dimensions: (usize, usize),
 }
 
 impl<S: State> PinkyWeb<S> {
     fn new(canvas: &Element, state: S) -> Self {
-        let gl = setup_webgl(&canvas);
+        let dimensions = state.get_frame_buffer_dimensions();
+        let (width, height) = (dimensions.0 as u32, dimensions.1 as u32);
+        let gl = setup_webgl(canvas, dimensions);
 
         let js_ctx = js!(
             var h = {};
@@ -219,7 +252,7 @@ impl<S: State> PinkyWeb<S> {
                 canvas = new_canvas;
 
                 h.ctx = canvas.getContext( "2d" );
-                h.img = h.ctx.createImageData( 256, 256 );
+                h.img = h.ctx.createImageData( @{width}, @{height} );
                 h.buffer = new Uint32Array( h.img.data.buffer );
             }
 
@@ -228,6 +261,7 @@ impl<S: State> PinkyWeb<S> {
 
         PinkyWeb {
             state,
+            dimensions,
             paused: true,
             busy: false,
             js_ctx,
@@ -246,6 +280,10 @@ impl<S: State> PinkyWeb<S> {
     fn execute_cycle(&mut self) -> Result<bool, Box<dyn Error>> {
         self.state.frame(handle_sound);
 
+        if let Some(result) = self.state.finished_export() {
+            save_export(result);
+        }
+
         Ok(true)
     }
 
@@ -276,6 +314,8 @@ impl<S: State> PinkyWeb<S> {
         if !self.paused {
             js! {
                 var h = @{&self.js_ctx};
+                var width = @{self.dimensions.0 as u32};
+                var height = @{self.dimensions.1 as u32};
                 var framebuffer = @{unsafe {
                     UnsafeTypedArray::new( self.state.get_frame_buffer() )
                  }};
@@ -286,7 +326,7 @@ impl<S: State> PinkyWeb<S> {
                         framebuffer.byteLength
                     );
                     h.gl.texSubImage2D( h.gl.TEXTURE_2D,
-                         0, 0, 0, 256, 256, h.gl.RGBA, h.gl.UNSIGNED_BYTE, data );
+                         0, 0, 0, width, height, h.gl.RGBA, h.gl.UNSIGNED_BYTE, data );
                     h.gl.drawElements( h.gl.TRIANGLES, 6, h.gl.UNSIGNED_SHORT, 0 );
                 } else {
                     h.buffer.set( framebuffer );
@@ -296,7 +336,50 @@ impl<S: State> PinkyWeb<S> {
         }
     }
 
+    fn screenshot(&mut self) {
+        let (width, height) = self.dimensions;
+        match export::encode_png(self.state.get_frame_buffer(), width as u32, height as u32) {
+            Ok(bytes) => download(&bytes, "256_squared_screenshot.png", "image/png"),
+            Err(error) => {
+                js!( console.error( "Screenshot error:", @{format!( "{}", error )} ); );
+            }
+        }
+    }
+
+    fn load_file(&mut self, name: &str, bytes: &[u8]) {
+        if let Err(error) = self.state.handle_command(Command::File { name, bytes }) {
+            js!( console.error( "Loading error:", @{format!( "{}", error )} ); );
+        }
+    }
+
+    // Returns whether the key was used.
+    fn handle_key(&mut self, key: &str) -> bool {
+        if let Some(result) = self.state.export(key) {
+            save_export(result);
+            return true;
+        }
+
+        match self.state.handle_command(Command::Key(key)) {
+            Ok(handled) => handled,
+            Err(error) => {
+                js!( console.error( "Command error:", @{format!( "{}", error )} ); );
+                true
+            }
+        }
+    }
+
     fn on_key(&mut self, key: &str, location: KeyboardLocation, is_pressed: bool) -> bool {
+        if is_pressed {
+            if key == "p" {
+                self.screenshot();
+                return true;
+            }
+
+            if self.handle_key(key) {
+                return true;
+            }
+        }
+
         let button = match (key, location) {
             ("Enter", _) => Button::Start,
             ("Shift", KeyboardLocation::Right) => Button::Select,
@@ -314,10 +397,17 @@ impl<S: State> PinkyWeb<S> {
 
             ("z", _) => Button::A,
             ("x", _) => Button::B,
+            ("c", _) => Button::X,
+            ("v", _) => Button::Y,
+            ("a", _) => Button::L,
+            ("s", _) => Button::R,
 
             // For those using the Dvorak layout.
             (";", _) => Button::A,
             ("q", _) => Button::B,
+            ("j", _) => Button::X,
+            ("k", _) => Button::Y,
+            ("o", _) => Button::R,
 
             // For those using the Dvorak layout **and** Microsoft Edge.
             //
@@ -424,6 +514,125 @@ fn support_input<S: State + 'static>(pinky: Rc<RefCell<PinkyWeb<S>>>) {
             event.prevent_default();
         }
     }));
+
+    // The canvas may be scaled by the page, so positions are converted to framebuffer
+    // pixels. That includes the side panel, if there is one.
+    let move_pointer = enclose!( [pinky] move |x: i32, y: i32, pressed: bool| {
+        let (width, height) = pinky.borrow().dimensions;
+        let pointer = if x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height {
+            Some(Pointer { x: x as usize, y: y as usize, pressed })
+        } else {
+            None
+        };
+        pinky.borrow_mut().state.move_pointer(pointer);
+    });
+
+    js! {
+        var move_pointer = @{move_pointer};
+        var canvas = document.getElementById( "viewport" );
+        var pressed = false;
+
+        var update = function( event ) {
+            var rect = canvas.getBoundingClientRect();
+            var x = Math.floor( (event.clientX - rect.left) * canvas.width / rect.width );
+            var y = Math.floor( (event.clientY - rect.top) * canvas.height / rect.height );
+            move_pointer( x, y, pressed );
+        };
+
+        canvas.addEventListener( "mousemove", update );
+        canvas.addEventListener( "mousedown", function( event ) {
+            pressed = true;
+            update( event );
+        });
+        window.addEventListener( "mouseup", function( event ) {
+            pressed = false;
+            update( event );
+        });
+        canvas.addEventListener( "mouseleave", function() {
+            move_pointer( -1, -1, false );
+        });
+    }
+
+    // Dropping a PNG onto the page replaces the picture the modes show, or the font or
+    // sprite sheet, depending on its name.
+    let load_file = enclose!( [pinky] move |name: String, bytes: TypedArray<u8>| {
+        pinky.borrow_mut().load_file(&name, &bytes.to_vec());
+    });
+
+    js! {
+        var load_file = @{load_file};
+
+        window.addEventListener( "dragover", function( event ) {
+            event.preventDefault();
+        });
+
+        window.addEventListener( "drop", function( event ) {
+            event.preventDefault();
+
+            var file = event.dataTransfer.files[0];
+            if( !file ) {
+                return;
+            }
+
+            var reader = new FileReader();
+            reader.onload = function() {
+                load_file( file.name, new Uint8Array( reader.result ) );
+            };
+            reader.onerror = function() {
+                console.error( "Could not read", file.name );
+            };
+            reader.readAsArrayBuffer( file );
+        });
+    }
+}
+
+// The samples come from the state as the browser asks for them, so a waveform that is
+// edited while playing is heard straight away. A `ScriptProcessorNode` is deprecated, but
+// unlike an `AudioWorklet` it can call back into the same instance as everything else.
+fn support_audio<S: State + 'static>(pinky: Rc<RefCell<PinkyWeb<S>>>) {
+    let fill_audio = enclose!( [pinky] move |sample_rate: u32, length: u32| -> TypedArray<f32> {
+        let mut samples = vec![0.0; length as usize];
+        pinky.borrow_mut().state.fill_audio(sample_rate, &mut samples);
+        TypedArray::from(&samples[..])
+    });
+
+    js! {
+        var fill_audio = @{fill_audio};
+
+        var AudioContext = window.AudioContext || window.webkitAudioContext;
+        if( !AudioContext ) {
+            console.log( "No WebAudio; sound is disabled" );
+            return;
+        }
+
+        var context = new AudioContext();
+        var processor = context.createScriptProcessor( 2048, 0, 1 );
+        processor.onaudioprocess = function( event ) {
+            var output = event.outputBuffer.getChannelData( 0 );
+            output.set( fill_audio( context.sampleRate, output.length ) );
+        };
+        processor.connect( context.destination );
+
+        // Sound effects are rendered all at once, so they are played separately.
+        window.playSamples = function( samples, sample_rate ) {
+            var buffer = context.createBuffer( 1, samples.length, sample_rate );
+            buffer.getChannelData( 0 ).set( samples );
+
+            var source = context.createBufferSource();
+            source.buffer = buffer;
+            source.connect( context.destination );
+            source.start();
+        };
+
+        // Browsers keep the audio suspended until the page has been interacted with.
+        var resume = function() {
+            if( context.state === "suspended" ) {
+                context.resume();
+            }
+        };
+        window.addEventListener( "keydown", resume );
+        window.addEventListener( "mousedown", resume );
+    }
 }
 
 fn handle_error<E: Into<Box<dyn Error>>>(error: E) {
@@ -445,6 +654,7 @@ pub fn run<S: State + 'static>(state: S) {
     let pinky = Rc::new(RefCell::new(PinkyWeb::new(&canvas, state)));
 
     support_input(pinky.clone());
+    support_audio(pinky.clone());
 
     hide("loading");
     hide("error");
@@ -466,5 +676,22 @@ pub fn get_state_params() -> StateParams {
 
         mem::transmute::<[f64; 2], [u8; 16]>([time, 1.0 / time])
     };
-    (seed, Some(logger), Some(error_logger))
+
+    // The framebuffer matches the canvas, so the page decides how much room there is
+    // around the grid.
+    let canvas = web::document().get_element_by_id("viewport");
+    let dimension = |name| {
+        canvas
+            .as_ref()
+            .and_then(|canvas| canvas.get_attribute(name))
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(256)
+    };
+
+    (
+        seed,
+        Some(logger),
+        Some(error_logger),
+        (dimension("width"), dimension("height")),
+    )
 }